@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    process::Command,
+};
+
+use serde::{Deserialize, Serialize};
+
+const RECORD_SEPARATOR: char = '\x1e';
+const UNIT_SEPARATOR: char = '\x1f';
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GitMeta {
+    pub created: String,
+    pub modified: String,
+    pub authors: Vec<String>,
+}
+
+impl GitMeta {
+    /// inserts `git_created`, `git_modified` and `git_authors` into the page metadata
+    pub fn insert_into(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert("git_created".to_string(), self.created.to_owned());
+        metadata.insert("git_modified".to_string(), self.modified.to_owned());
+        metadata.insert("git_authors".to_string(), self.authors.join(", "));
+    }
+}
+
+/// history of every file under a directory, keyed by the path relative to that directory
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GitHistory {
+    head: String,
+    dir: String,
+    files: HashMap<String, GitMeta>,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = match Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "core.quotepath=false"])
+        .args(args)
+        .output()
+    {
+        Ok(o) => o,
+        Err(e) => return Err(format!("cannot execute git: {}", e)),
+    };
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+impl GitHistory {
+    /// reads the history of `dir` from the cache file,
+    /// or from `git log` when the cache was written for another HEAD
+    pub fn load(dir: &Path, cache_file: &Path) -> Result<Self, String> {
+        let head = git(dir, &["rev-parse", "HEAD"])?.trim().to_string();
+        let dir_str = dir.to_string_lossy().to_string();
+
+        if let Ok(s) = fs::read_to_string(cache_file) {
+            if let Ok(cached) = serde_json::from_str::<Self>(&s) {
+                if cached.head == head && cached.dir == dir_str {
+                    return Ok(cached);
+                }
+            }
+        }
+
+        let log = git(
+            dir,
+            &[
+                "log",
+                &format!("--format={}%aI{}%an", RECORD_SEPARATOR, UNIT_SEPARATOR),
+                "--name-only",
+                "--relative",
+                "--",
+                ".",
+            ],
+        )?;
+
+        let history = Self {
+            head,
+            dir: dir_str,
+            files: parse_log(&log),
+        };
+
+        if let Err(e) = history.save(cache_file) {
+            eprintln!("{}: {}", cache_file.to_string_lossy(), e);
+        }
+
+        Ok(history)
+    }
+
+    fn save(&self, cache_file: &Path) -> Result<(), String> {
+        if let Some(parent) = cache_file.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        let mut file = File::create(cache_file).map_err(|e| e.to_string())?;
+        file.write_all(json.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    pub fn get(&self, relative_path: &Path) -> Option<&GitMeta> {
+        // git always uses '/' as the separator
+        let key = relative_path
+            .to_string_lossy()
+            .replace(std::path::MAIN_SEPARATOR, "/");
+        self.files.get(&key)
+    }
+}
+
+/// builds the metadata of each file from `git log` output (newest commit first)
+fn parse_log(log: &str) -> HashMap<String, GitMeta> {
+    let mut files: HashMap<String, GitMeta> = HashMap::new();
+
+    for record in log.split(RECORD_SEPARATOR).filter(|r| !r.is_empty()) {
+        let mut lines = record.lines();
+        let Some(header) = lines.next() else {
+            continue;
+        };
+        let Some((date, author)) = header.split_once(UNIT_SEPARATOR) else {
+            continue;
+        };
+
+        for name in lines.filter(|l| !l.is_empty()) {
+            let meta = files.entry(name.to_string()).or_insert_with(|| GitMeta {
+                created: date.to_string(),
+                modified: date.to_string(),
+                authors: vec![],
+            });
+
+            // older commits come later, so the last one seen is the creation
+            meta.created = date.to_string();
+            if !meta.authors.iter().any(|a| a == author) {
+                meta.authors.push(author.to_string());
+            }
+        }
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_log;
+
+    #[test]
+    fn test_parse_log() {
+        let log = "\x1e2022-11-20T10:00:00+09:00\x1fpiyopiyo\n\na.md\nb.md\n\x1e2022-11-19T10:00:00+09:00\x1fhogehoge\n\na.md\n";
+        let files = parse_log(log);
+
+        let a = &files["a.md"];
+        assert_eq!(a.created, "2022-11-19T10:00:00+09:00");
+        assert_eq!(a.modified, "2022-11-20T10:00:00+09:00");
+        assert_eq!(a.authors, vec!["piyopiyo", "hogehoge"]);
+
+        let b = &files["b.md"];
+        assert_eq!(b.created, "2022-11-20T10:00:00+09:00");
+        assert_eq!(b.modified, "2022-11-20T10:00:00+09:00");
+        assert_eq!(b.authors, vec!["piyopiyo"]);
+    }
+}
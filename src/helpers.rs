@@ -0,0 +1,498 @@
+use std::{
+    collections::HashMap,
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Locale, NaiveDate, NaiveDateTime, TimeZone};
+use fnv::FnvHasher;
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderError,
+    ScopedJson,
+};
+use pulldown_cmark::html;
+use rhai::{
+    serde::{from_dynamic, to_dynamic},
+    Dynamic, Engine, Scope, AST,
+};
+
+use crate::html_gen;
+use crate::settings::settings::Settings;
+use crate::slug::slugify;
+
+const NAIVE_DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+];
+const NAIVE_DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%Y/%m/%d"];
+
+// execution limits of the script helpers
+const SCRIPT_MAX_OPERATIONS: u64 = 100_000;
+const SCRIPT_MAX_CALL_LEVELS: usize = 32;
+const SCRIPT_MAX_EXPR_DEPTH: usize = 64;
+const SCRIPT_MAX_STRING_SIZE: usize = 1024 * 1024;
+const SCRIPT_MAX_ARRAY_SIZE: usize = 10_000;
+const SCRIPT_MAX_MAP_SIZE: usize = 10_000;
+
+/// registers the built-in helpers
+pub fn register(registry: &mut Handlebars, settings: &Settings) {
+    registry.register_helper("date_format", Box::new(date_format));
+    registry.register_helper("slugify", Box::new(slugify_helper));
+    registry.register_helper(
+        "markdown",
+        Box::new(MarkdownHelper {
+            settings: settings.clone(),
+        }),
+    );
+    registry.register_helper("relative_url", Box::new(relative_url_helper));
+    registry.register_helper(
+        "asset_url",
+        Box::new(AssetUrlHelper {
+            html_dir: PathBuf::from(&settings.output.html_dir),
+        }),
+    );
+    registry.register_helper("truncate", Box::new(truncate_helper));
+    registry.register_helper("json", Box::new(json_helper));
+}
+
+/// rhai engine for the script helpers, which must be set before the scripts are registered
+pub fn script_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(SCRIPT_MAX_OPERATIONS)
+        .set_max_call_levels(SCRIPT_MAX_CALL_LEVELS)
+        .set_max_expr_depths(SCRIPT_MAX_EXPR_DEPTH, SCRIPT_MAX_EXPR_DEPTH)
+        .set_max_string_size(SCRIPT_MAX_STRING_SIZE)
+        .set_max_array_size(SCRIPT_MAX_ARRAY_SIZE)
+        .set_max_map_size(SCRIPT_MAX_MAP_SIZE)
+        .disable_symbol("eval");
+    // scripts must not write to the console
+    engine.on_print(|_| {}).on_debug(|_, _, _| {});
+    engine
+}
+
+/// registers `path` (e.g. `helpers/percent.rhai`) as the script helper `name`
+pub fn register_script(registry: &mut Handlebars, name: &str, path: &Path) -> Result<(), String> {
+    let script = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => return Err(format!("{}: {}", path.to_string_lossy(), e)),
+    };
+
+    register_script_source(registry, name, path, &script)
+}
+
+fn register_script_source(
+    registry: &mut Handlebars,
+    name: &str,
+    path: &Path,
+    script: &str,
+) -> Result<(), String> {
+    let script = match registry.engine().compile(script) {
+        Ok(a) => a,
+        Err(e) => return Err(format!("{}: {}", path.to_string_lossy(), e)),
+    };
+
+    registry.register_helper(
+        name,
+        Box::new(ScriptHelper {
+            script,
+            path: path.to_path_buf(),
+        }),
+    );
+    Ok(())
+}
+
+/// same as the script helper of handlebars (`params` and `hash` are available in the script),
+/// but the errors are reported with the script file
+struct ScriptHelper {
+    script: AST,
+    path: PathBuf,
+}
+
+impl ScriptHelper {
+    fn error<E: std::fmt::Display>(&self, e: E) -> RenderError {
+        RenderError::new(format!("{}: {}", self.path.to_string_lossy(), e))
+    }
+}
+
+impl HelperDef for ScriptHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let params = h.params().iter().map(|p| p.value()).collect::<Vec<_>>();
+        let hash = h
+            .hash()
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.value()))
+            .collect::<HashMap<_, _>>();
+
+        let mut scope = Scope::new();
+        scope.push_dynamic("params", to_dynamic(params).map_err(|e| self.error(e))?);
+        scope.push_dynamic("hash", to_dynamic(hash).map_err(|e| self.error(e))?);
+
+        let result = r
+            .engine()
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.script)
+            .map_err(|e| self.error(e))?;
+
+        match from_dynamic(&result) {
+            Ok(v) => Ok(ScopedJson::Derived(v)),
+            Err(e) => Err(self.error(e)),
+        }
+    }
+}
+
+fn param_str<'a>(h: &'a Helper, idx: usize) -> Result<&'a str, RenderError> {
+    match h.param(idx).and_then(|p| p.value().as_str()) {
+        Some(s) => Ok(s),
+        None => Err(RenderError::new(format!(
+            "{}: parameter {} must be a string",
+            h.name(),
+            idx
+        ))),
+    }
+}
+
+/// path of the page being rendered, relative to `output.html_dir`
+fn current_page(ctx: &Context) -> &str {
+    ctx.data()
+        .get("path")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+}
+
+fn parse_date(s: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(d) = DateTime::parse_from_rfc3339(s) {
+        return Some(d);
+    }
+
+    let utc = FixedOffset::east_opt(0)?;
+
+    for f in NAIVE_DATETIME_FORMATS.iter() {
+        if let Ok(d) = NaiveDateTime::parse_from_str(s, f) {
+            return utc.from_local_datetime(&d).single();
+        }
+    }
+
+    for f in NAIVE_DATE_FORMATS.iter() {
+        if let Ok(d) = NaiveDate::parse_from_str(s, f) {
+            return utc.from_local_datetime(&d.and_hms_opt(0, 0, 0)?).single();
+        }
+    }
+
+    None
+}
+
+fn format_date(date: &str, format: &str, locale: &str) -> Result<String, String> {
+    let Some(d) = parse_date(date) else {
+        return Err(format!("cannot parse date: {}", date));
+    };
+
+    let Ok(l) = Locale::try_from(locale) else {
+        return Err(format!("unknown locale: {}", locale));
+    };
+
+    Ok(d.format_localized(format, l).to_string())
+}
+
+/// `{{date_format meta.date "%Y年%m月%d日" locale="ja_JP"}}`
+fn date_format(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let date = param_str(h, 0)?;
+    let format = param_str(h, 1)?;
+    let locale = h
+        .hash_get("locale")
+        .and_then(|v| v.value().as_str())
+        .unwrap_or("POSIX");
+
+    let formatted = format_date(date, format, locale).map_err(RenderError::new)?;
+    out.write(&formatted)?;
+    Ok(())
+}
+
+/// `{{slugify meta.title}}`
+fn slugify_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&slugify(param_str(h, 0)?))?;
+    Ok(())
+}
+
+/// `{{{markdown meta.summary}}}`
+struct MarkdownHelper {
+    settings: Settings,
+}
+
+impl MarkdownHelper {
+    fn render(&self, markdown: &str) -> String {
+        let mut metadata: HashMap<String, String> = HashMap::new();
+        let parser = html_gen::setup_parser(
+            markdown,
+            html_gen::parser_options(&self.settings),
+            &self.settings,
+            &mut metadata,
+        );
+
+        let mut html = String::new();
+        html::push_html(&mut html, parser);
+
+        // an inline string is rendered without the surrounding paragraph
+        let trimmed = html.trim_end();
+        if trimmed.starts_with("<p>")
+            && trimmed.ends_with("</p>")
+            && trimmed.matches("<p>").count() == 1
+        {
+            return trimmed[3..trimmed.len() - 4].to_string();
+        }
+
+        html
+    }
+}
+
+impl HelperDef for MarkdownHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        out.write(&self.render(param_str(h, 0)?))?;
+        Ok(())
+    }
+}
+
+fn relative_url(page: &str, url: &str) -> String {
+    if url.contains("://")
+        || url.starts_with('/')
+        || url.starts_with('#')
+        || url.starts_with("data:")
+        || url.starts_with("mailto:")
+    {
+        return url.to_string();
+    }
+
+    let depth = page.matches('/').count();
+    format!("{}{}", "../".repeat(depth), url)
+}
+
+/// `{{relative_url "css/site.css"}}`, the url is relative to `output.html_dir`
+fn relative_url_helper(
+    h: &Helper,
+    _: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&relative_url(current_page(ctx), param_str(h, 0)?))?;
+    Ok(())
+}
+
+/// `{{asset_url "css/site.css"}}`, like `relative_url` with a fingerprint of the file content
+struct AssetUrlHelper {
+    html_dir: PathBuf,
+}
+
+impl HelperDef for AssetUrlHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let url = param_str(h, 0)?;
+        let path = self.html_dir.join(url);
+        let content = match fs::read(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(RenderError::new(format!(
+                    "{}: {}",
+                    path.to_string_lossy(),
+                    e
+                )))
+            }
+        };
+
+        out.write(&format!(
+            "{}?v={}",
+            relative_url(current_page(ctx), url),
+            fingerprint(&content)
+        ))?;
+        Ok(())
+    }
+}
+
+/// FNV-1a of the content, which stays the same over the Rust versions unlike `DefaultHasher`
+fn fingerprint(content: &[u8]) -> String {
+    let mut hasher = FnvHasher::default();
+    hasher.write(content);
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+fn truncate(text: &str, length: usize, suffix: &str) -> String {
+    if text.chars().count() <= length {
+        return text.to_string();
+    }
+
+    let mut result = text.chars().take(length).collect::<String>();
+    result.push_str(suffix);
+    result
+}
+
+/// `{{truncate meta.summary 100}}` or `{{truncate meta.summary 100 "..."}}`
+fn truncate_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let text = param_str(h, 0)?;
+    let Some(length) = h.param(1).and_then(|p| p.value().as_u64()) else {
+        return Err(RenderError::new("truncate: parameter 1 must be a number"));
+    };
+    let suffix = match h.param(2) {
+        Some(_) => param_str(h, 2)?,
+        None => "…",
+    };
+
+    out.write(&truncate(text, length as usize, suffix))?;
+    Ok(())
+}
+
+/// `{{{json meta}}}`
+fn json_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let Some(param) = h.param(0) else {
+        return Err(RenderError::new("json: parameter 0 is required"));
+    };
+
+    match serde_json::to_string(param.value()) {
+        Ok(s) => out.write(&s)?,
+        Err(e) => return Err(RenderError::from_error("json", e)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use super::{
+        fingerprint, format_date, register_script_source, relative_url, script_engine, truncate,
+    };
+
+    fn script_registry(script: &str) -> Result<Handlebars<'static>, String> {
+        let mut registry = Handlebars::new();
+        registry.set_engine(script_engine());
+        register_script_source(
+            &mut registry,
+            "script",
+            Path::new("helpers/script.rhai"),
+            script,
+        )?;
+        Ok(registry)
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(
+            format_date("2022/11/19", "%Y-%m-%d", "POSIX").unwrap(),
+            "2022-11-19"
+        );
+        assert_eq!(
+            format_date("2022-11-19T10:20:30+09:00", "%Y年%m月%d日 %H:%M", "ja_JP").unwrap(),
+            "2022年11月19日 10:20"
+        );
+        assert_eq!(
+            format_date("2022-11-19", "%A, %B %d", "ja_JP").unwrap(),
+            "土曜日, 11月 19"
+        );
+        assert!(format_date("yesterday", "%Y", "POSIX").is_err());
+        assert!(format_date("2022-11-19", "%Y", "xx_XX").is_err());
+    }
+
+    #[test]
+    fn test_relative_url() {
+        assert_eq!(relative_url("index.html", "css/site.css"), "css/site.css");
+        assert_eq!(
+            relative_url("blog/2022/post.html", "css/site.css"),
+            "../../css/site.css"
+        );
+        assert_eq!(
+            relative_url("blog/post.html", "https://example.com/"),
+            "https://example.com/"
+        );
+        assert_eq!(relative_url("blog/post.html", "#top"), "#top");
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(b"body {}"), "ff2b8953");
+        assert_eq!(fingerprint(b""), "84222325");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("hello", 5, "…"), "hello");
+        assert_eq!(truncate("hello world", 5, "…"), "hello…");
+        assert_eq!(truncate("テストデータ", 3, "..."), "テスト...");
+        assert_eq!(truncate("𩸽𩸽𩸽", 2, ""), "𩸽𩸽");
+    }
+
+    #[test]
+    fn test_script_helper() {
+        let registry = script_registry("let v = params[0];\nv * hash.rate").unwrap();
+        let output = registry
+            .render_template("{{script 21 rate=2}}", &json!({}))
+            .unwrap();
+        assert_eq!(output, "42");
+    }
+
+    #[test]
+    fn test_script_helper_parse_error() {
+        let Err(e) = script_registry("let v = 1;\nlet = ;") else {
+            panic!("parse error expected");
+        };
+        assert!(e.starts_with("helpers/script.rhai: "), "{}", e);
+        assert!(e.contains("line 2"), "{}", e);
+    }
+
+    #[test]
+    fn test_script_helper_runtime_error() {
+        let registry = script_registry("let x = 0;\nloop { x += 1; }").unwrap();
+        let e = registry
+            .render_template("{{script}}", &json!({}))
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("helpers/script.rhai: "), "{}", e);
+        assert!(e.contains("line 2"), "{}", e);
+    }
+}
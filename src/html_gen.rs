@@ -1,72 +1,266 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range, path::Path};
 
-use pulldown_cmark::{Event, Tag};
+use pulldown_cmark::{Event, Options, Parser, Tag};
 
+use crate::md_event::footnote;
+use crate::md_event::toc::{self, TocItem};
 use crate::md_event::workarea::Workarea;
-use crate::md_event::{end, html, start, text};
+use crate::md_event::{admonition, end, heading, html, start, text};
+use crate::preprocess::Preprocessor;
 use crate::settings::settings::Settings;
 
-pub fn setup_parser<'a, I>(
-    iter: I,
+/// the parser options enabled in `[markdown]`
+pub fn parser_options(settings: &Settings) -> Options {
+    let markdown = &settings.markdown;
+    let mut options = Options::empty();
+    options.set(Options::ENABLE_TABLES, markdown.tables);
+    options.set(Options::ENABLE_FOOTNOTES, markdown.footnotes);
+    options.set(Options::ENABLE_STRIKETHROUGH, markdown.strikethrough);
+    options.set(Options::ENABLE_TASKLISTS, markdown.tasklists);
+    options.set(
+        Options::ENABLE_SMART_PUNCTUATION,
+        markdown.smart_punctuation,
+    );
+    options.set(
+        Options::ENABLE_HEADING_ATTRIBUTES,
+        markdown.heading_attributes,
+    );
+    options
+}
+
+/// parses `text`, which is not read from a file (e.g. a string in the metadata).
+/// relative paths in the Markdown are resolved from the current directory.
+pub fn setup_parser<'a>(
+    text: &'a str,
+    options: Options,
+    settings: &Settings,
+    metadata: &mut HashMap<String, String>,
+) -> impl Iterator<Item = Event<'a>> {
+    let mut workarea = process(
+        Parser::new_ext(text, options).into_offset_iter(),
+        settings,
+        Workarea::for_source(vec![], options, text),
+    );
+
+    let toc = toc::render(&toc::build(&workarea.headings, settings));
+    toc::insert(&mut workarea.events, &toc);
+    footnote::collect(&mut workarea.events, settings);
+
+    metadata.extend(workarea.meta.into_iter());
+    workarea.events.into_iter()
+}
+
+/// same as `setup_parser`, but parses `text` read from `path`.
+/// relative paths in the Markdown (e.g. includes) are resolved from `path`.
+/// the table of contents is also stored into `toc`,
+/// and whether the page has tabs (which need the script) into `has_tabs`.
+/// `preprocessor` interpolates the included files as it did `text`.
+pub fn setup_file_parser<'a>(
+    text: &'a str,
+    preprocessor: Option<&'a Preprocessor<'a>>,
+    path: &Path,
     settings: &Settings,
     metadata: &mut HashMap<String, String>,
-) -> impl Iterator<Item = Event<'a>>
+    toc: &mut Vec<TocItem>,
+    has_tabs: &mut bool,
+) -> impl Iterator<Item = Event<'a>> {
+    let options = parser_options(settings);
+    let mut workarea = Workarea::for_source(vec![path.to_path_buf()], options, text);
+    workarea.preprocessor = preprocessor;
+    let mut workarea = process(
+        Parser::new_ext(text, options).into_offset_iter(),
+        settings,
+        workarea,
+    );
+
+    *toc = toc::build(&workarea.headings, settings);
+    *has_tabs = workarea.tab_groups > 0;
+    toc::insert(&mut workarea.events, &toc::render(toc));
+    footnote::collect(&mut workarea.events, settings);
+
+    metadata.extend(workarea.meta);
+    workarea.events.into_iter()
+}
+
+/// code blocks with the `file` attribute in `text` read from `path`, as (file, code).
+/// only the code blocks and the includes are read, so no code is run or checked.
+pub fn tangle(text: &str, options: Options, path: &Path) -> Vec<(String, String)> {
+    let workarea = collect_tangles(
+        Parser::new_ext(text, options).into_offset_iter(),
+        Workarea::for_source(vec![path.to_path_buf()], options, text),
+    );
+
+    workarea.tangles
+}
+
+/// same as `tangle`, for an included Markdown file
+pub fn tangle_included<'a>(text: &'a str, workarea: Workarea<'a>) -> Workarea<'a> {
+    collect_tangles(
+        Parser::new_ext(text, workarea.options).into_offset_iter(),
+        workarea,
+    )
+}
+
+/// parses an included Markdown file into `workarea` made for it
+pub fn process_included<'a>(
+    text: &'a str,
+    workarea: Workarea<'a>,
+    settings: &Settings,
+) -> Workarea<'a> {
+    process(
+        Parser::new_ext(text, workarea.options).into_offset_iter(),
+        settings,
+        workarea,
+    )
+}
+
+fn process<'a, I>(iter: I, settings: &Settings, mut workarea: Workarea<'a>) -> Workarea<'a>
 where
-    I: Iterator<Item = Event<'a>>,
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
 {
-    let mut workarea = Workarea::new();
+    // the metadata (e.g. `:numbered:`) applies to the whole page wherever it is written
+    workarea.meta.extend(collect_metadata(Parser::new_ext(
+        workarea.source,
+        workarea.options,
+    )));
 
-    iter.for_each(|ev| match &ev {
-        Event::Start(start_tag) => match &start_tag {
-            Tag::CodeBlock(kind) => {
-                // println!("event: Start (CodeBlock)");
-                workarea.break_frags();
-                workarea.is_code = true;
-                start::event_code(&mut workarea, &settings, &ev, kind);
+    for (ev, range) in iter {
+        workarea.offset = range.start;
+
+        match &ev {
+            Event::Start(start_tag) => match &start_tag {
+                Tag::CodeBlock(kind) => {
+                    // println!("event: Start (CodeBlock)");
+                    workarea.break_frags();
+                    workarea.is_code = true;
+                    start::event_code(&mut workarea, &settings, &ev, kind);
+                }
+                Tag::Image(link_type, src, title) => {
+                    start::event_image(&mut workarea, &settings, &ev, link_type, src, title);
+                }
+                Tag::Heading(level, id, classes) => {
+                    heading::start(&mut workarea, *level, *id, classes);
+                }
+                Tag::Paragraph => {
+                    admonition::start_paragraph(&mut workarea, &ev);
+                }
+                Tag::BlockQuote => {
+                    admonition::start_quote(&mut workarea, &ev);
+                }
+                Tag::Strikethrough if text::is_subscript(&workarea, settings) => {
+                    workarea.push_event(&Event::Html("<sub>".into()));
+                }
+                _ => {
+                    // println!("event: Start (Other)");
+                    workarea.push_event(&ev);
+                }
+            },
+
+            Event::End(end_tag) => match &end_tag {
+                Tag::CodeBlock(kind) => {
+                    // println!("event: End (CodeBlock)");
+                    workarea.break_frags();
+                    workarea.is_code = true;
+                    end::event_code(&mut workarea, &settings, &ev, &kind);
+                    workarea.is_code = false;
+                }
+                Tag::Heading(..) => {
+                    heading::end(&mut workarea, settings);
+                }
+                Tag::Paragraph => {
+                    admonition::end_paragraph(&mut workarea, settings, &ev);
+                }
+                Tag::BlockQuote => {
+                    admonition::end_quote(&mut workarea, settings, &ev);
+                }
+                Tag::Strikethrough if text::is_subscript(&workarea, settings) => {
+                    workarea.push_event(&Event::Html("</sub>".into()));
+                }
+                _ => {
+                    // println!("event: End (Other)");
+                    workarea.push_event(&ev);
+                }
+            },
+            Event::Text(content) => {
+                // println!("event: Text => {}", content);
+                // keep flags
+                text::event(&mut workarea, &settings, &ev, &content);
             }
-            Tag::Image(link_type, src, title) => {
-                start::event_image(&mut workarea, &settings, &ev, link_type, src, title);
+            Event::Html(content) => {
+                // println!("event: Html => {}", content);
+                let is_comment = workarea.is_comment; // backup
+                workarea.break_frags();
+                workarea.is_html = true;
+                workarea.is_comment = is_comment; // restore
+                html::event(&mut workarea, &settings, &ev, &content);
             }
             _ => {
-                // println!("event: Start (Other)");
+                // println!("event: ???");
                 workarea.push_event(&ev);
             }
-        },
+        }
+    }
+
+    admonition::end_containers(&mut workarea);
+
+    workarea
+}
 
-        Event::End(end_tag) => match &end_tag {
-            Tag::CodeBlock(kind) => {
-                // println!("event: End (CodeBlock)");
+fn collect_tangles<'a, I>(iter: I, mut workarea: Workarea<'a>) -> Workarea<'a>
+where
+    I: Iterator<Item = (Event<'a>, Range<usize>)>,
+{
+    for (ev, range) in iter {
+        workarea.offset = range.start;
+
+        match &ev {
+            Event::Start(Tag::CodeBlock(_)) => {
                 workarea.break_frags();
                 workarea.is_code = true;
-                end::event_code(&mut workarea, &settings, &ev, &kind);
+            }
+            Event::End(Tag::CodeBlock(kind)) => {
+                end::tangle(&mut workarea, kind);
                 workarea.is_code = false;
             }
-            _ => {
-                // println!("event: End (Other)");
-                workarea.push_event(&ev);
+            Event::Text(content) if workarea.is_code => {
+                workarea.push_content(&content.to_string());
+            }
+            Event::Html(content) => {
+                let is_comment = workarea.is_comment; // backup
+                workarea.break_frags();
+                workarea.is_html = true;
+                workarea.is_comment = is_comment; // restore
+                html::tangle(&mut workarea, content);
             }
-        },
-        Event::Text(content) => {
-            // println!("event: Text => {}", content);
-            // keep flags
-            text::event(&mut workarea, &settings, &ev, &content);
+            _ => {}
+        }
+    }
+
+    workarea
+}
+
+/// collects the comment metadata without processing the other events
+pub fn collect_metadata<'a, I>(iter: I) -> HashMap<String, String>
+where
+    I: Iterator<Item = Event<'a>>,
+{
+    let mut workarea = Workarea::new();
+
+    iter.for_each(|ev| match &ev {
+        Event::Start(Tag::CodeBlock(_)) | Event::End(Tag::CodeBlock(_)) => {
+            workarea.break_frags();
         }
         Event::Html(content) => {
-            // println!("event: Html => {}", content);
             let is_comment = workarea.is_comment; // backup
             workarea.break_frags();
             workarea.is_html = true;
             workarea.is_comment = is_comment; // restore
-            html::event(&mut workarea, &settings, &ev, &content);
-        }
-        _ => {
-            // println!("event: ???");
-            workarea.push_event(&ev);
+            html::metadata(&mut workarea, content);
         }
+        _ => {}
     });
 
-    metadata.extend(workarea.meta.into_iter());
-    workarea.events.into_iter()
+    workarea.meta
 }
 
 #[cfg(test)]
@@ -74,29 +268,78 @@ mod tests {
     use std::{
         borrow::Cow,
         collections::{BTreeMap, HashMap},
+        fs,
+        path::Path,
     };
 
+    use crate::preprocess::Preprocessor;
+    use crate::settings::admonition::AdmonitionType;
+    use crate::settings::markdown::Markdown;
     use crate::settings::settings::Settings;
-    use pulldown_cmark::{html, Options, Parser};
+    use crate::test_dir::TestDir;
+    use handlebars::Handlebars;
+    use pulldown_cmark::{html, Parser};
+    use serde_json::json;
 
-    use super::setup_parser;
+    use super::{collect_metadata, parser_options, setup_file_parser, setup_parser, tangle};
 
     fn generate(markdown: &str) -> (Cow<'_, str>, HashMap<String, String>) {
-        let settings = Settings::new(None, None, None);
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_STRIKETHROUGH);
-        options.insert(Options::ENABLE_TABLES);
-        options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+        generate_with(
+            markdown,
+            &Settings::new(None, None, None, None, None, None, None, None, None, None),
+        )
+    }
 
+    fn generate_with<'a>(
+        markdown: &'a str,
+        settings: &Settings,
+    ) -> (Cow<'a, str>, HashMap<String, String>) {
+        let options = parser_options(settings);
         let mut metadata: HashMap<String, String> = HashMap::new();
 
-        let parser = setup_parser(Parser::new_ext(markdown, options), &settings, &mut metadata);
+        let parser = setup_parser(markdown, options, settings, &mut metadata);
         let mut html = String::new();
         html::push_html(&mut html, parser);
 
         (html.into(), metadata)
     }
 
+    /// renders `markdown` with an extension enabled and disabled
+    fn generate_toggled(
+        markdown: &str,
+        toggle: fn(&mut Markdown) -> &mut bool,
+    ) -> (String, String) {
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        *toggle(&mut settings.markdown) = true;
+        let (enabled, _) = generate_with(markdown, &settings);
+        *toggle(&mut settings.markdown) = false;
+        let (disabled, _) = generate_with(markdown, &settings);
+
+        (enabled.to_string(), disabled.to_string())
+    }
+
+    fn generate_file(path: &Path) -> String {
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let text = fs::read_to_string(path).unwrap();
+        let mut metadata: HashMap<String, String> = HashMap::new();
+
+        let mut toc = vec![];
+        let parser = setup_file_parser(
+            &text,
+            None,
+            path,
+            &settings,
+            &mut metadata,
+            &mut toc,
+            &mut false,
+        );
+        let mut html = String::new();
+        html::push_html(&mut html, parser);
+
+        html
+    }
+
     #[test]
     fn test_metadata() {
         let md = r#"# Hello
@@ -124,7 +367,7 @@ mod tests {
 plain text
 "#;
 
-        let expected_output = r#"<h1>Hello</h1>
+        let expected_output = r#"<h1 id="hello">Hello</h1>
 <!-- :test: data -->
 <!--
     :ignored2: test
@@ -153,6 +396,27 @@ plain text
         assert_eq!(expected_output, actual_output);
     }
 
+    #[test]
+    fn test_metadata_without_value() {
+        let md = "<!--- :draft: --->\n<!--- :toc: --->\n<!--- :author: hoge --->\n# テスト\n";
+        let expected_metadata: BTreeMap<String, String> =
+            BTreeMap::from([("author".to_string(), "hoge".to_string())]);
+
+        let (_, actual_metadata) = generate(md);
+        assert_eq!(
+            expected_metadata,
+            actual_metadata
+                .into_iter()
+                .collect::<BTreeMap<String, String>>(),
+        );
+        assert_eq!(
+            expected_metadata,
+            collect_metadata(Parser::new(md))
+                .into_iter()
+                .collect::<BTreeMap<String, String>>(),
+        );
+    }
+
     #[test]
     fn test_metadata_with_emoji() {
         let md = r#"# Hello
@@ -180,7 +444,7 @@ plain text
 plain text
 "#;
 
-        let expected_output = r#"<h1>Hello</h1>
+        let expected_output = r#"<h1 id="hello">Hello</h1>
 <!-- :🚿test🚿: 🚿data🚿 -->
 <!--
     :🚿ignored2🚿: 🚿test🚿
@@ -239,7 +503,7 @@ plain text
 plain text
 "#;
 
-        let expected_output = r#"<h1>Hello</h1>
+        let expected_output = r#"<h1 id="hello">Hello</h1>
 <!-- :𩸽test𩸽: 𩸽data𩸽 -->
 <!--
     :𩸽ignored2𩸽: 𩸽test𩸽
@@ -270,4 +534,612 @@ plain text
         );
         assert_eq!(expected_output, actual_output);
     }
+
+    #[test]
+    fn test_include() {
+        let dir = TestDir::new("test_include");
+        let page = dir.write(
+            "page.md",
+            "# Page\n<!--- :include: snippets/install.md shift=1 --->\n<!--- :author: hoge --->\n",
+        );
+        dir.write(
+            "snippets/install.md",
+            "# Install\n\n<!--- :include: warning.md --->\n",
+        );
+        dir.write(
+            "snippets/warning.md",
+            "**Warning**\n\n<!--- :include: install.md --->\n",
+        );
+
+        let expected_output = r#"<h1 id="page">Page</h1>
+<h2 id="install">Install</h2>
+<p><strong>Warning</strong></p>
+"#;
+        assert_eq!(expected_output, generate_file(&page));
+    }
+
+    #[test]
+    fn test_include_preprocessed() {
+        let dir = TestDir::new("test_include_preprocessed");
+        let page = dir.write("page.md", "<!--- :include: snippets/a.md --->\n");
+        dir.write("snippets/a.md", "# Snip {{ site.name }}\n");
+
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let data = json!({"site": {"name": "mtoh"}});
+        let registry = Handlebars::new();
+        let preprocessor = Preprocessor::new(&data, &registry);
+        let text = fs::read_to_string(&page).unwrap();
+        let parser = setup_file_parser(
+            &text,
+            Some(&preprocessor),
+            &page,
+            &settings,
+            &mut HashMap::new(),
+            &mut vec![],
+            &mut false,
+        );
+        let mut html = String::new();
+        html::push_html(&mut html, parser);
+
+        assert_eq!("<h1 id=\"snip-mtoh\">Snip mtoh</h1>\n", html);
+    }
+
+    #[test]
+    fn test_tangle_include() {
+        let dir = TestDir::new("test_tangle_include");
+        let path = dir.write(
+            "page.md",
+            "```sh file=run.sh\necho page\n```\n\n<!--- :include: snippets/a.md --->\n",
+        );
+        dir.write(
+            "snippets/a.md",
+            "```sh file=run.sh\necho snippet\n```\n\n```sh\necho ignored\n```\n",
+        );
+
+        let text = fs::read_to_string(&path).unwrap();
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let expected = vec![
+            ("run.sh".to_string(), "echo page\n".to_string()),
+            ("run.sh".to_string(), "echo snippet\n".to_string()),
+        ];
+        assert_eq!(expected, tangle(&text, parser_options(&settings), &path));
+    }
+
+    #[test]
+    fn test_code_span_highlight() {
+        let md = "`let x = 1;`{:rust} `x`{:rust}:shower: `{:rust}` {:rust}\n";
+        let expected_output = concat!(
+            r#"<p><code class="language-rust" style="background-color:#fdf6e3;">"#,
+            r#"<span style="color:#268bd2;">let</span><span style="color:#657b83;"> x </span>"#,
+            r#"<span style="color:#859900;">= </span><span style="color:#6c71c4;">1</span>"#,
+            r#"<span style="color:#657b83;">;</span></code> "#,
+            r#"<code class="language-rust" style="background-color:#fdf6e3;">"#,
+            r#"<span style="color:#657b83;">x</span></code>🚿 <code>{:rust}</code> {:rust}</p>"#,
+            "\n"
+        );
+        let (actual_output, _) = generate(md);
+        assert_eq!(expected_output, actual_output);
+    }
+
+    #[test]
+    fn test_indented_code_block() {
+        let md = "text\n\n    fn main() {\n        println!(\"<テスト>\");\n    }\n\ntext\n";
+        let expected_output = r#"<p>text</p>
+<pre><code>fn main() {
+    println!(&quot;&lt;テスト&gt;&quot;);
+}
+</code></pre>
+<p>text</p>
+"#;
+        let (actual_output, _) = generate(md);
+        assert_eq!(expected_output, actual_output);
+    }
+
+    #[test]
+    fn test_fenced_code_block_without_language() {
+        let md = "```\n<a href=\"#\">𩸽</a>\n```\n\n~~~\n&amp;\n~~~\n";
+        let expected_output = r#"<pre><code>&lt;a href=&quot;#&quot;&gt;𩸽&lt;/a&gt;
+</code></pre>
+<pre><code>&amp;amp;
+</code></pre>
+"#;
+        let (actual_output, _) = generate(md);
+        assert_eq!(expected_output, actual_output);
+    }
+
+    #[test]
+    fn test_code_block_default_language() {
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.code.highlight.default_language = "rust".to_string();
+
+        let md = "    let x = 1;\n";
+        let (actual_output, _) = generate_with(md, &settings);
+        assert!(actual_output.starts_with("<pre><code><pre style=\"background-color:#fdf6e3;\">\n"));
+        assert!(actual_output.contains(r#"<span style="color:#268bd2;">let</span>"#));
+        assert!(actual_output.ends_with("</pre>\n</code></pre>\n"));
+    }
+
+    #[test]
+    fn test_tabs() {
+        let md = r#"<!--- :tabs: --->
+
+```sh
+echo 🚿
+```
+
+```console {title="シェル"}
+$ echo 🚿
+```
+
+<!--- :endtabs: --->
+"#;
+        let expected_output = r#"<div class="tabs">
+<div role="tablist" hidden>
+<button type="button" role="tab" id="tabs-0-0" aria-controls="tabs-0-0-panel" aria-selected="true" tabindex="0">sh</button>
+<button type="button" role="tab" id="tabs-0-1" aria-controls="tabs-0-1-panel" aria-selected="false" tabindex="-1">シェル</button>
+</div>
+<div role="tabpanel" id="tabs-0-0-panel" aria-labelledby="tabs-0-0" tabindex="0">
+<pre><code class="language-sh"><pre style="background-color:#fdf6e3;">
+<span style="color:#657b83;">echo 🚿
+</span></pre>
+</code></pre>
+</div>
+<div role="tabpanel" id="tabs-0-1-panel" aria-labelledby="tabs-0-1" tabindex="0">
+<pre><code class="language-console"><span class="prompt">$ </span><span class="command">echo 🚿</span>
+</code></pre>
+</div>
+</div>
+"#;
+        let (actual_output, actual_metadata) = generate(md);
+        assert_eq!(expected_output, actual_output);
+        assert!(actual_metadata.is_empty());
+    }
+
+    #[test]
+    fn test_tabs_include() {
+        let dir = TestDir::new("test_tabs_include");
+        let tabs = "<!--- :tabs: --->\n\n```sh\necho 🚿\n```\n\n<!--- :endtabs: --->\n";
+        let page = dir.write(
+            "page.md",
+            format!("{}\n<!--- :include: snippet.md --->\n", tabs),
+        );
+        dir.write("snippet.md", tabs);
+
+        let html = generate_file(&page);
+        assert!(html.contains(r#"id="tabs-0-0""#));
+        assert!(html.contains(r#"id="tabs-1-0""#));
+    }
+
+    #[test]
+    fn test_markdown_tables() {
+        let (enabled, disabled) =
+            generate_toggled("| テスト |\n|---|\n| 🚿 |\n", |m| &mut m.tables);
+        assert_eq!(
+            enabled,
+            "<table><thead><tr><th>テスト</th></tr></thead><tbody>\n<tr><td>🚿</td></tr>\n</tbody></table>\n"
+        );
+        assert_eq!(disabled, "<p>| テスト |\n|---|\n| 🚿 |</p>\n");
+    }
+
+    #[test]
+    fn test_markdown_footnotes() {
+        let (enabled, disabled) =
+            generate_toggled("𩸽[^1]\n\n[^1]: ほっけ\n", |m| &mut m.footnotes);
+        assert_eq!(
+            enabled,
+            r##"<p>𩸽<sup class="footnote-ref" id="fnref-1"><a href="#fn-1">1</a></sup></p>
+<section class="footnotes">
+<ol>
+<li id="fn-1">
+<p>ほっけ <a href="#fnref-1" class="footnote-backref">↩</a></p>
+</li>
+</ol>
+</section>
+"##
+        );
+        // `[^1]: ほっけ` is a link reference definition without the extension
+        assert_eq!(
+            disabled,
+            "<p>𩸽<a href=\"%E3%81%BB%E3%81%A3%E3%81%91\">^1</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_strikethrough() {
+        let (enabled, disabled) = generate_toggled("~~テスト~~\n", |m| &mut m.strikethrough);
+        assert_eq!(enabled, "<p><del>テスト</del></p>\n");
+        assert_eq!(disabled, "<p>~~テスト~~</p>\n");
+    }
+
+    #[test]
+    fn test_markdown_tasklists() {
+        let (enabled, disabled) =
+            generate_toggled("- [x] テスト\n- [ ] 🚿\n", |m| &mut m.tasklists);
+        assert_eq!(
+            enabled,
+            r#"<ul>
+<li><input disabled="" type="checkbox" checked=""/>
+テスト</li>
+<li><input disabled="" type="checkbox"/>
+🚿</li>
+</ul>
+"#
+        );
+        assert_eq!(
+            disabled,
+            "<ul>\n<li>[x] テスト</li>\n<li>[ ] 🚿</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_smart_punctuation() {
+        let (enabled, disabled) = generate_toggled("\"テスト\" -- 'a' --- b...\n", |m| {
+            &mut m.smart_punctuation
+        });
+        assert_eq!(enabled, "<p>“テスト” – ‘a’ — b…</p>\n");
+        assert_eq!(disabled, "<p>&quot;テスト&quot; -- 'a' --- b...</p>\n");
+    }
+
+    #[test]
+    fn test_markdown_heading_attributes() {
+        let (enabled, disabled) = generate_toggled("# テスト {#test .title}\n", |m| {
+            &mut m.heading_attributes
+        });
+        assert_eq!(enabled, "<h1 id=\"test\" class=\"title\">テスト</h1>\n");
+        assert_eq!(
+            disabled,
+            "<h1 id=\"テスト-test-title\">テスト {#test .title}</h1>\n"
+        );
+    }
+
+    #[test]
+    fn test_heading_ids() {
+        let md = r#"# はじめに
+
+## Getting *Started* 🚀
+
+## Getting `Started`
+
+## 𩸽 {#hokke .fish}
+
+### はじめに
+"#;
+        let expected_output = r#"<h1 id="はじめに">はじめに</h1>
+<h2 id="getting-started">Getting <em>Started</em> 🚀</h2>
+<h2 id="getting-started-1">Getting <code>Started</code></h2>
+<h2 id="hokke" class="fish">𩸽</h2>
+<h3 id="はじめに-1">はじめに</h3>
+"#;
+        let (actual_output, _) = generate(md);
+        assert_eq!(expected_output, actual_output);
+
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.heading.ids = false;
+        let (actual_output, _) = generate_with("# はじめに {#intro}\n## 🚿\n", &settings);
+        assert_eq!(
+            "<h1 id=\"intro\">はじめに</h1>\n<h2>🚿</h2>\n",
+            actual_output
+        );
+    }
+
+    #[test]
+    fn test_heading_anchors() {
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.heading.anchors = true;
+
+        let (actual_output, _) = generate_with("## インストール\n", &settings);
+        assert_eq!(
+            r##"<h2 id="インストール">インストール<a class="anchor" href="#インストール" aria-hidden="true">#</a></h2>
+"##,
+            actual_output
+        );
+    }
+
+    #[test]
+    fn test_toc() {
+        let md = r#"# Title
+
+<!--- :toc: --->
+
+## はじめに
+
+### `mtoh` の🚿
+
+#### Ignored
+
+## [𩸽](https://example.com/) {#hokke}
+"#;
+        let expected_output = r##"<h1 id="title">Title</h1>
+<nav class="toc">
+<ul>
+<li><a href="#はじめに">はじめに</a>
+<ul>
+<li><a href="#mtoh-の"><code>mtoh</code> の🚿</a></li>
+</ul>
+</li>
+<li><a href="#hokke">𩸽</a></li>
+</ul>
+</nav>
+<h2 id="はじめに">はじめに</h2>
+<h3 id="mtoh-の"><code>mtoh</code> の🚿</h3>
+<h4 id="ignored">Ignored</h4>
+<h2 id="hokke"><a href="https://example.com/">𩸽</a></h2>
+"##;
+        let (actual_output, actual_metadata) = generate(md);
+        assert_eq!(expected_output, actual_output);
+        assert!(actual_metadata.is_empty());
+    }
+
+    #[test]
+    fn test_numbered_headings() {
+        let md = r#"<!--- :numbered: true --->
+# Title {.unnumbered}
+
+<!--- :toc: --->
+
+## はじめに
+
+### 🚿
+
+## 付録 {.unnumbered}
+
+## 𩸽
+"#;
+        let expected_output = r##"<h1 id="title" class="unnumbered">Title</h1>
+<nav class="toc">
+<ul>
+<li><a href="#はじめに"><span class="section-number">1</span> はじめに</a>
+<ul>
+<li><a href="#section"><span class="section-number">1.1</span> 🚿</a></li>
+</ul>
+</li>
+<li><a href="#付録">付録</a></li>
+<li><a href="#𩸽"><span class="section-number">2</span> 𩸽</a></li>
+</ul>
+</nav>
+<h2 id="はじめに"><span class="section-number">1</span> はじめに</h2>
+<h3 id="section"><span class="section-number">1.1</span> 🚿</h3>
+<h2 id="付録" class="unnumbered">付録</h2>
+<h2 id="𩸽"><span class="section-number">2</span> 𩸽</h2>
+"##;
+        let (actual_output, _) = generate(md);
+        assert_eq!(expected_output, actual_output);
+
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.markdown.number_headings = true;
+        let (actual_output, _) = generate_with("## A\n### B\n", &settings);
+        assert_eq!(
+            r#"<h2 id="a"><span class="section-number">1</span> A</h2>
+<h3 id="b"><span class="section-number">1.1</span> B</h3>
+"#,
+            actual_output
+        );
+
+        let md = "<!--- :numbered: false --->\n## A\n";
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!("<h2 id=\"a\">A</h2>\n", actual_output);
+
+        // numbered from h1, and `:numbered:` after the headings applies to all of them
+        let md = "# A\n## B\n<!--- :numbered: true --->\n";
+        assert_eq!(
+            r#"<h1 id="a"><span class="section-number">1</span> A</h1>
+<h2 id="b"><span class="section-number">1.1</span> B</h2>
+"#,
+            generate(md).0
+        );
+    }
+
+    #[test]
+    fn test_admonition_callout() {
+        let md = r#"> [!NOTE]
+> Useful information.
+
+> [!WARNING] 注意
+> **Urgent** info.
+
+> [!TIP]- More
+> Hidden.
+
+> [!UNKNOWN]
+> Quote.
+"#;
+        let expected_output = r#"<div class="admonition note">
+<p class="admonition-title"><span class="admonition-icon">ℹ️</span> Note</p>
+<p>Useful information.</p>
+</div>
+<div class="admonition warning">
+<p class="admonition-title"><span class="admonition-icon">⚠️</span> 注意</p>
+<p><strong>Urgent</strong> info.</p>
+</div>
+<details class="admonition tip">
+<summary class="admonition-title"><span class="admonition-icon">💡</span> More</summary>
+<p>Hidden.</p>
+</details>
+<blockquote>
+<p>[!UNKNOWN]
+Quote.</p>
+</blockquote>
+"#;
+        let (actual_output, _) = generate(md);
+        assert_eq!(expected_output, actual_output);
+    }
+
+    #[test]
+    fn test_admonition_container() {
+        let md = r#":::tip
+Use `mtoh`.
+:::
+
+:::caution+ Careful
+
+Text.
+
+- item
+
+:::
+"#;
+        let expected_output = r#"<div class="admonition tip">
+<p class="admonition-title"><span class="admonition-icon">💡</span> Tip</p>
+<p>Use <code>mtoh</code>.</p>
+</div>
+<details class="admonition caution" open>
+<summary class="admonition-title"><span class="admonition-icon">🛑</span> Careful</summary>
+<p>Text.</p>
+<ul>
+<li>item</li>
+</ul>
+</details>
+"#;
+        let (actual_output, _) = generate(md);
+        assert_eq!(expected_output, actual_output);
+
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        // the type in the settings is also case-insensitive
+        settings.admonition.types.insert(
+            "Info".to_string(),
+            AdmonitionType {
+                label: "お知らせ".to_string(),
+                icon: String::new(),
+            },
+        );
+        let (actual_output, _) = generate_with(":::info\n:::\n", &settings);
+        assert_eq!(
+            "<div class=\"admonition info\">\n<p class=\"admonition-title\">お知らせ</p>\n</div>\n",
+            actual_output
+        );
+
+        // closed at the end of the file
+        let (actual_output, _) = generate_with(":::info\n\nText.\n", &settings);
+        assert_eq!(
+            "<div class=\"admonition info\">\n<p class=\"admonition-title\">お知らせ</p>\n<p>Text.</p>\n</div>\n",
+            actual_output
+        );
+    }
+
+    #[test]
+    fn test_footnote_endnotes() {
+        let md = r#"a[^b] c[^x] d[^b] e[^🚿]
+
+[^🚿]: シャワー
+
+[^b]: *B*
+
+[^unused]: 未使用
+"#;
+        let expected_output = r##"<p>a<sup class="footnote-ref" id="fnref-1"><a href="#fn-1">1</a></sup> c[^x] d<sup class="footnote-ref" id="fnref-1-2"><a href="#fn-1">1</a></sup> e<sup class="footnote-ref" id="fnref-2"><a href="#fn-2">2</a></sup></p>
+<section class="footnotes">
+<ol>
+<li id="fn-1">
+<p><em>B</em> <a href="#fnref-1" class="footnote-backref">↩</a> <a href="#fnref-1-2" class="footnote-backref">↩<sup>2</sup></a></p>
+</li>
+<li id="fn-2">
+<p>シャワー <a href="#fnref-2" class="footnote-backref">↩</a></p>
+</li>
+</ol>
+</section>
+"##;
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.markdown.footnotes = true;
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!(expected_output, actual_output);
+    }
+
+    #[test]
+    fn test_footnote_in_footnote() {
+        let md = "a[^1]\n\n[^1]: note with[^2]\n\n[^2]: nested[^1]\n";
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.markdown.footnotes = true;
+
+        let expected_output = r##"<p>a<sup class="footnote-ref" id="fnref-1"><a href="#fn-1">1</a></sup></p>
+<section class="footnotes">
+<ol>
+<li id="fn-1">
+<p>note with<sup class="footnote-ref" id="fnref-2"><a href="#fn-2">2</a></sup> <a href="#fnref-1" class="footnote-backref">↩</a> <a href="#fnref-1-2" class="footnote-backref">↩<sup>2</sup></a></p>
+</li>
+<li id="fn-2">
+<p>nested<sup class="footnote-ref" id="fnref-1-2"><a href="#fn-1">1</a></sup> <a href="#fnref-2" class="footnote-backref">↩</a></p>
+</li>
+</ol>
+</section>
+"##;
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!(expected_output, actual_output);
+
+        settings.footnote.style = "sidenote".to_string();
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!(
+            r##"<p>a<sup class="footnote-ref" id="fnref-1">1</sup><span class="sidenote" id="fn-1"><sup class="sidenote-number">1</sup> note with<sup class="footnote-ref" id="fnref-2">2</sup><span class="sidenote" id="fn-2"><sup class="sidenote-number">2</sup> nested<sup class="footnote-ref" id="fnref-1-2"><a href="#fn-1">1</a></sup></span></span></p>
+"##,
+            actual_output
+        );
+    }
+
+    #[test]
+    fn test_footnote_sidenotes() {
+        let md = "a[^1] b[^1]\n\n[^1]: 𩸽 *ほっけ*\n";
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.markdown.footnotes = true;
+
+        settings.footnote.style = "sidenote".to_string();
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!(
+            r##"<p>a<sup class="footnote-ref" id="fnref-1">1</sup><span class="sidenote" id="fn-1"><sup class="sidenote-number">1</sup> 𩸽 <em>ほっけ</em></span> b<sup class="footnote-ref" id="fnref-1-2"><a href="#fn-1">1</a></sup></p>
+"##,
+            actual_output
+        );
+
+        settings.footnote.style = "popover".to_string();
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!(
+            r##"<p>a<sup class="footnote-ref" id="fnref-1"><button type="button" popovertarget="fn-1">1</button></sup><span class="footnote-popover" id="fn-1" popover>𩸽 <em>ほっけ</em></span> b<sup class="footnote-ref" id="fnref-1-2"><a href="#fn-1">1</a></sup></p>
+"##,
+            actual_output
+        );
+    }
+
+    #[test]
+    fn test_inline_markup() {
+        let md = "Press [[Ctrl]]+[[C]], ==not== *H~2~O* ~sub~ ~~del~~\n";
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!(
+            "<p>Press [[Ctrl]]+[[C]], ==not== <em>H~2~O</em> <del>sub</del> <del>del</del></p>\n",
+            actual_output
+        );
+
+        // a single tilde is a strikethrough by pulldown-cmark, told from the source text
+        settings.markdown.keyboard = true;
+        settings.markdown.subscript = true;
+        let (actual_output, _) = generate_with(md, &settings);
+        assert_eq!(
+            "<p>Press <kbd>Ctrl</kbd>+<kbd>C</kbd>, ==not== <em>H<sub>2</sub>O</em> <sub>sub</sub> <del>del</del></p>\n",
+            actual_output
+        );
+
+        let mut metadata = HashMap::new();
+        let parser = setup_file_parser(
+            md,
+            None,
+            Path::new("page.md"),
+            &settings,
+            &mut metadata,
+            &mut vec![],
+            &mut false,
+        );
+        let mut actual_output = String::new();
+        html::push_html(&mut actual_output, parser);
+        assert_eq!(
+            "<p>Press <kbd>Ctrl</kbd>+<kbd>C</kbd>, ==not== <em>H<sub>2</sub>O</em> <sub>sub</sub> <del>del</del></p>\n",
+            actual_output
+        );
+    }
 }
@@ -1,9 +1,16 @@
+mod git_meta;
+mod helpers;
 mod html_gen;
 mod md_event;
+mod preprocess;
 mod settings;
+mod slug;
+mod tangle;
+#[cfg(test)]
+mod test_dir;
 
 use handlebars::Handlebars;
-use pulldown_cmark::{html, Options, Parser};
+use pulldown_cmark::{html, Parser};
 use serde_json::json;
 use std::{
     collections::HashMap,
@@ -12,6 +19,9 @@ use std::{
     path::{Path, PathBuf, MAIN_SEPARATOR},
 };
 
+use crate::git_meta::GitHistory;
+use crate::md_event::{tabs, toc};
+use crate::preprocess::Preprocessor;
 use crate::settings::settings::Settings;
 
 fn enum_files(path: &Path, recursive: bool, callback: &mut dyn FnMut(&Path)) -> Result<(), String> {
@@ -94,6 +104,129 @@ fn make_html_directory_from(settings: &Settings) -> Result<PathBuf, String> {
     make_directory_path_with_exists_check(&settings.output.html_dir)
 }
 
+fn make_template_directory_from(settings: &Settings) -> Result<PathBuf, String> {
+    make_directory_path_with_exists_check(&settings.input.template_dir)
+}
+
+/// registers every `*.hbs` in the template directory by its file stem,
+/// every `*.hbs` under `partials/` by its relative path (e.g. `shortcodes/youtube`)
+/// and every `*.rhai` under `helpers/` as a script helper named by its file stem
+fn build_registry(template_dir: &Path) -> Result<Handlebars<'static>, String> {
+    let mut registry = Handlebars::new();
+    registry.set_engine(helpers::script_engine());
+
+    let mut template_files: Vec<PathBuf> = vec![];
+    enum_files(template_dir, false, &mut |p: &Path| {
+        if p.is_file() && p.extension().is_some_and(|e| e == "hbs") {
+            template_files.push(p.to_path_buf());
+        }
+    })?;
+
+    for template in template_files.iter() {
+        let Some(name) = template.file_stem() else {
+            continue;
+        };
+
+        if let Err(e) = registry.register_template_file(&name.to_string_lossy(), template) {
+            return Err(format!("{}: {}", template.to_string_lossy(), e));
+        }
+    }
+
+    let helpers_dir = template_dir.join("helpers");
+    let mut script_files: Vec<PathBuf> = vec![];
+    if helpers_dir.is_dir() {
+        enum_files(&helpers_dir, false, &mut |p: &Path| {
+            if p.is_file() && p.extension().is_some_and(|e| e == "rhai") {
+                script_files.push(p.to_path_buf());
+            }
+        })?;
+    }
+
+    for script in script_files.iter() {
+        let Some(name) = script.file_stem() else {
+            continue;
+        };
+
+        helpers::register_script(&mut registry, &name.to_string_lossy(), script)?;
+    }
+
+    let partials_dir = template_dir.join("partials");
+    let mut partial_files: Vec<PathBuf> = vec![];
+    if partials_dir.is_dir() {
+        enum_files(&partials_dir, true, &mut |p: &Path| {
+            if p.is_file() && p.extension().is_some_and(|e| e == "hbs") {
+                partial_files.push(p.to_path_buf());
+            }
+        })?;
+    }
+
+    for partial in partial_files.iter() {
+        let name = partial
+            .strip_prefix(&partials_dir)
+            .unwrap()
+            .with_extension("")
+            .to_string_lossy()
+            // for windows
+            .replace(MAIN_SEPARATOR, "/");
+
+        let text = match fs::read_to_string(partial) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", partial.to_string_lossy(), e)),
+        };
+
+        if let Err(e) = registry.register_partial(&name, text) {
+            return Err(format!("{}: {}", partial.to_string_lossy(), e));
+        }
+    }
+
+    Ok(registry)
+}
+
+/// `:layout:` metadata first, then the nearest directory default, then the default layout
+fn select_layout(
+    settings: &Settings,
+    metadata: &HashMap<String, String>,
+    relative_path: &Path,
+) -> String {
+    if let Some(layout) = metadata.get("layout") {
+        return layout.to_owned();
+    }
+
+    let mut dir = relative_path.parent();
+    while let Some(d) = dir {
+        let key = d.to_string_lossy().replace(MAIN_SEPARATOR, "/");
+        let keys = if key.is_empty() {
+            vec!["".to_string(), ".".to_string()]
+        } else {
+            vec![key]
+        };
+
+        for k in keys.iter() {
+            if let Some(layout) = settings.template.directories.get(k) {
+                return layout.to_owned();
+            }
+        }
+
+        dir = d.parent();
+    }
+
+    settings.template.default_layout.to_owned()
+}
+
+/// whether `path` under the markdown directory is rendered as a page,
+/// the images, the snippets and the files other than `*.md` are not
+fn is_page(settings: &Settings, path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "md")
+        && !path.starts_with(&settings.input.img_dir)
+        && !path.starts_with(&settings.input.snippets_dir)
+}
+
+/// `../` for each directory of `relative_path` under the markdown directory
+fn root_prefix(relative_path: &Path) -> String {
+    let depth = relative_path.parent().map_or(0, |p| p.components().count());
+    "../".repeat(depth)
+}
+
 fn main() {
     println!("Hello, world!");
 
@@ -110,31 +243,20 @@ fn main() {
         settings.version, settings.code.highlight.theme,
     );
 
-    // let mut template_files: Vec<PathBuf> = vec![];
-    // let template_dir_path = Path::new(&settings.input.template_dir);
-    // if !template_dir_path.exists() {
-    //     eprintln!("{} is not exists.", template_dir_path.to_string_lossy());
-    //     std::process::exit(1);
-    // }
-
-    // match enum_files(&template_dir_path, false, &mut |p: &Path| {
-    //     template_files.push(p.to_path_buf());
-    // }) {
-    //     Ok(()) => {}
-    //     Err(e) => {
-    //         eprintln!("{}", e);
-    //         std::process::exit(1);
-    //     }
-    // }
+    // mtoh tangle [output directory]
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|a| a == "tangle") {
+        let out_dir = match args.get(2) {
+            Some(d) => PathBuf::from(d),
+            None => PathBuf::from(&settings.output.tangle_dir),
+        };
 
-    // for template in template_files.iter() {
-    //     let text = match fs::read_to_string(template) {
-    //         Ok(s) => s,
-    //         Err(e) => {
-    //             eprintln!("{}", e);
-    //             std::process::exit(1);
-    //         }
-    //     };
+        if let Err(e) = tangle::run(&settings, &out_dir) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     // let ps = SyntaxSet::load_defaults_newlines();
     // for ele in ps.syntaxes() {
@@ -155,8 +277,8 @@ fn main() {
         }
     };
 
-    match enum_files(&markdown_dir_path, false, &mut |p: &Path| {
-        if p.is_file() {
+    match enum_files(&markdown_dir_path, true, &mut |p: &Path| {
+        if p.is_file() && is_page(&settings, p) {
             markdown_files.push(p.to_path_buf());
         }
     }) {
@@ -167,6 +289,18 @@ fn main() {
         }
     }
 
+    let git_history = if settings.git.enabled {
+        match GitHistory::load(&markdown_dir_path, Path::new(&settings.git.cache_file)) {
+            Ok(h) => Some(h),
+            Err(e) => {
+                eprintln!("git metadata is disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     let mut sass_files: Vec<PathBuf> = vec![];
     let sass_dir_path = match make_scss_directory_from(&settings) {
         Ok(p) => p,
@@ -259,31 +393,33 @@ fn main() {
             .to_path_buf()
     }));
 
-    let link_tags = css_files
-        .iter()
-        .map(|s| {
-            format!(
-                r#"<link href="{}" rel="stylesheet" type="text/css" />"#,
-                // for windows
-                s.to_string_lossy().replace(MAIN_SEPARATOR, "/")
-            )
-        })
-        .collect::<Vec<String>>()
-        .join("\n");
+    // the script is written when the first page with tabs is found
+    let js_dir_path = PathBuf::from(&settings.output.js_dir);
+    let script_path = js_dir_path.join(tabs::SCRIPT_NAME);
+    let mut script_written = false;
+    let script = script_path
+        .strip_prefix(html_dir_path.as_path())
+        .unwrap_or(&script_path)
+        .to_path_buf();
 
-    let html_template =
-        match fs::read_to_string(Path::new(&settings.input.template_dir).join("code.hbs")) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            }
-        };
+    let template_dir_path = match make_template_directory_from(&settings) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    let mut registry = match build_registry(&template_dir_path) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    helpers::register(&mut registry, &settings);
+
+    let options = html_gen::parser_options(&settings);
 
     for md in markdown_files.iter() {
         let text = match fs::read_to_string(md) {
@@ -295,31 +431,129 @@ fn main() {
         };
 
         let mut metadata: HashMap<String, String> = HashMap::new();
-        let parser = html_gen::setup_parser(
-            Parser::new_ext(text.as_str(), options),
+        let relative = md.strip_prefix(&markdown_dir_path).unwrap_or(md);
+        if let Some(history) = &git_history {
+            if let Some(meta) = history.get(relative) {
+                meta.insert_into(&mut metadata);
+            }
+        }
+
+        // metadata and site variables are interpolated before parsing
+        metadata.extend(html_gen::collect_metadata(Parser::new_ext(
+            text.as_str(),
+            options,
+        )));
+        let variables = json!({
+            "meta": metadata,
+            "site": settings.site,
+            "vars": settings.vars,
+        });
+        let preprocessor = Preprocessor::new(&variables, &registry);
+        let text = preprocessor.run(md, &text);
+
+        let mut toc = vec![];
+        let mut has_tabs = false;
+        let parser = html_gen::setup_file_parser(
+            text.as_str(),
+            Some(&preprocessor),
+            md,
             &settings,
             &mut metadata,
+            &mut toc,
+            &mut has_tabs,
         );
 
         let mut html = String::new();
         html::push_html(&mut html, parser);
 
-        let reg = Handlebars::new();
-        let output = reg
-            .render_template(
-                &html_template.as_str(),
-                &json!({"title": "test", "content": html, "css_link": link_tags.as_str()}),
+        // the pages in the subdirectories link the files from the parent directories
+        let root = root_prefix(relative);
+        let link_tags = css_files
+            .iter()
+            .map(|s| {
+                format!(
+                    r#"<link href="{}{}" rel="stylesheet" type="text/css" />"#,
+                    root,
+                    // for windows
+                    s.to_string_lossy().replace(MAIN_SEPARATOR, "/")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        let script_tags = if has_tabs {
+            // the directory is created, since only the scripts of mtoh are written into it
+            if !script_written {
+                let written = fs::create_dir_all(&js_dir_path)
+                    .and_then(|_| fs::write(&script_path, tabs::SCRIPT));
+                if let Err(e) = written {
+                    eprintln!("{}: {}", script_path.to_string_lossy(), e);
+                    std::process::exit(1);
+                }
+                script_written = true;
+            }
+
+            format!(
+                r#"<script src="{}{}" defer></script>"#,
+                root,
+                // for windows
+                script.to_string_lossy().replace(MAIN_SEPARATOR, "/")
             )
-            .unwrap();
+        } else {
+            String::new()
+        };
+
+        let layout = select_layout(&settings, &metadata, relative);
+        if !registry.has_template(&layout) {
+            eprintln!(
+                "{}: layout \"{}\" not found in {}",
+                md.to_string_lossy(),
+                layout,
+                template_dir_path.to_string_lossy()
+            );
+            std::process::exit(1);
+        }
 
         let new_name = match make_output_html_filename(md) {
-            Ok(p) => p,
+            Ok(p) => match relative.parent() {
+                Some(d) => d.join(p),
+                None => p,
+            },
             Err(e) => {
                 eprintln!("{}", e);
                 std::process::exit(1);
             }
         };
-        let result = html_dir_path.join(new_name);
+
+        let output = match registry.render(
+            &layout,
+            &json!({
+                "title": "test",
+                "content": html,
+                "css_link": link_tags.as_str(),
+                "js_link": script_tags.as_str(),
+                "toc": toc::render(&toc),
+                "toc_data": toc,
+                "meta": metadata,
+                "site": settings.site,
+                "vars": settings.vars,
+                // for windows
+                "path": new_name.to_string_lossy().replace(MAIN_SEPARATOR, "/"),
+            }),
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("{}: {}", md.to_string_lossy(), e);
+                std::process::exit(1);
+            }
+        };
+
+        let result = html_dir_path.join(&new_name);
+        if let Some(parent) = result.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                eprintln!("{}: {}", parent.to_string_lossy(), e);
+                std::process::exit(1);
+            }
+        }
 
         let mut file = match File::create(result) {
             Ok(f) => f,
@@ -339,3 +573,65 @@ fn main() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::Path};
+
+    use crate::settings::settings::Settings;
+
+    use super::{is_page, root_prefix, select_layout};
+
+    #[test]
+    fn test_select_layout() {
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.template.default_layout = "page".to_string();
+        settings
+            .template
+            .directories
+            .insert("blog".to_string(), "post".to_string());
+        settings
+            .template
+            .directories
+            .insert("blog/2024".to_string(), "archive".to_string());
+
+        let mut metadata = HashMap::new();
+        let layout = |metadata: &HashMap<String, String>, path: &str| {
+            select_layout(&settings, metadata, Path::new(path))
+        };
+
+        // the default layout
+        assert_eq!(layout(&metadata, "index.md"), "page");
+        assert_eq!(layout(&metadata, "docs/intro.md"), "page");
+
+        // the nearest directory
+        assert_eq!(layout(&metadata, "blog/hello.md"), "post");
+        assert_eq!(layout(&metadata, "blog/2023/old.md"), "post");
+        assert_eq!(layout(&metadata, "blog/2024/new.md"), "archive");
+
+        // the metadata
+        metadata.insert("layout".to_string(), "slide".to_string());
+        assert_eq!(layout(&metadata, "index.md"), "slide");
+        assert_eq!(layout(&metadata, "blog/2024/new.md"), "slide");
+    }
+
+    #[test]
+    fn test_is_page() {
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        assert!(is_page(&settings, Path::new("md/index.md")));
+        assert!(is_page(&settings, Path::new("md/blog/hello.md")));
+        assert!(!is_page(&settings, Path::new("md/img/logo.png")));
+        assert!(!is_page(&settings, Path::new("md/img/readme.md")));
+        assert!(!is_page(&settings, Path::new("md/snippets/warning.md")));
+        assert!(!is_page(&settings, Path::new("md/snippets/install/linux.md")));
+        assert!(!is_page(&settings, Path::new("md/notes.txt")));
+    }
+
+    #[test]
+    fn test_root_prefix() {
+        assert_eq!(root_prefix(Path::new("index.md")), "");
+        assert_eq!(root_prefix(Path::new("blog/hello.md")), "../");
+        assert_eq!(root_prefix(Path::new("blog/2024/new.md")), "../../");
+    }
+}
@@ -0,0 +1,199 @@
+use pulldown_cmark::{escape::escape_html, Event, Tag};
+use regex::Captures;
+
+use super::workarea::Workarea;
+use crate::settings::admonition::AdmonitionType;
+use crate::settings::settings::Settings;
+
+/// the last line of a `:::` container
+const CONTAINER_END: &str = ":::";
+
+pub fn start_paragraph<'a>(workarea: &mut Workarea<'a>, ev: &Event<'a>) {
+    workarea.paragraph_begin = Some(workarea.events.len());
+    workarea.push_event(ev);
+}
+
+/// a paragraph starting with `:::tip [title]` opens a container,
+/// and a paragraph ending with `:::` closes it
+pub fn end_paragraph<'a>(workarea: &mut Workarea<'a>, settings: &Settings, ev: &Event<'a>) {
+    workarea.push_event(ev);
+    let Some(begin) = workarea.paragraph_begin.take() else {
+        return;
+    };
+    let content = begin + 1;
+
+    let mut open_tag = None;
+    if let Some((line, len)) = first_line(&workarea.events[content..]) {
+        if let Some(tag) = workarea
+            .re
+            .admonition_container
+            .captures(&line)
+            .and_then(|c| open(settings, &c))
+        {
+            workarea.events.drain(content..content + len);
+            workarea.containers.push((tag.1, workarea.line()));
+            open_tag = Some(tag.0);
+        }
+    }
+
+    let mut close_tag = None;
+    if !workarea.containers.is_empty() {
+        let end = workarea.events.len() - 1;
+        if let Some((line, len)) = last_line(&workarea.events[content..end]) {
+            if line.trim() == CONTAINER_END {
+                workarea.events.drain(end - len..end);
+                close_tag = workarea.containers.pop().map(|(c, _)| close(c));
+            }
+        }
+    }
+
+    // the paragraph had only the markers
+    if workarea.events.len() == begin + 2 {
+        workarea.events.truncate(begin);
+    }
+    if let Some(tag) = open_tag {
+        workarea.events.insert(begin, Event::Html(tag.into()));
+    }
+    if let Some(tag) = close_tag {
+        workarea.push_event(&Event::Html(tag.into()));
+    }
+}
+
+pub fn start_quote<'a>(workarea: &mut Workarea<'a>, ev: &Event<'a>) {
+    workarea.quote_begins.push(workarea.events.len());
+    workarea.push_event(ev);
+}
+
+/// a block quote starting with `[!NOTE]` (GitHub) is an admonition
+pub fn end_quote<'a>(workarea: &mut Workarea<'a>, settings: &Settings, ev: &Event<'a>) {
+    let Some(begin) = workarea.quote_begins.pop() else {
+        workarea.push_event(ev);
+        return;
+    };
+
+    if !matches!(
+        workarea.events.get(begin + 1),
+        Some(Event::Start(Tag::Paragraph))
+    ) {
+        workarea.push_event(ev);
+        return;
+    }
+
+    let content = begin + 2;
+    let tag = first_line(&workarea.events[content..]).and_then(|(line, len)| {
+        workarea
+            .re
+            .admonition_callout
+            .captures(&line)
+            .and_then(|c| open(settings, &c))
+            .map(|tag| (tag, len))
+    });
+    let Some(((open_tag, is_details), len)) = tag else {
+        workarea.push_event(ev);
+        return;
+    };
+
+    workarea.events.drain(content..content + len);
+    if matches!(
+        workarea.events.get(content),
+        Some(Event::End(Tag::Paragraph))
+    ) {
+        workarea.events.drain(begin + 1..=content);
+    }
+    workarea.events[begin] = Event::Html(open_tag.into());
+    workarea.push_event(&Event::Html(close(is_details).into()));
+}
+
+/// the opening tag of the admonition, and whether it is collapsible (`<details>`).
+/// `None` when the type is unknown.
+fn open(settings: &Settings, captures: &Captures) -> Option<(String, bool)> {
+    let kind = captures["kind"].to_lowercase();
+    let AdmonitionType { label, icon } = settings.admonition.get(&kind)?;
+    let fold = captures.name("fold").map(|m| m.as_str());
+    let title = captures
+        .name("title")
+        .map(|m| m.as_str().trim())
+        .filter(|t| !t.is_empty())
+        .unwrap_or(&label);
+
+    let mut title_html = String::new();
+    if !icon.is_empty() {
+        title_html.push_str(&format!(
+            r#"<span class="admonition-icon">{}</span> "#,
+            icon
+        ));
+    }
+    escape_html(&mut title_html, title).unwrap();
+
+    let mut class = String::new();
+    escape_html(&mut class, &kind).unwrap();
+
+    let tag = match fold {
+        Some(f) => format!(
+            "<details class=\"admonition {}\"{}>\n<summary class=\"admonition-title\">{}</summary>\n",
+            class,
+            if f == "+" { " open" } else { "" },
+            title_html
+        ),
+        None => format!(
+            "<div class=\"admonition {}\">\n<p class=\"admonition-title\">{}</p>\n",
+            class, title_html
+        ),
+    };
+
+    Some((tag, fold.is_some()))
+}
+
+/// closes the `:::` containers left open at the end of the file
+pub fn end_containers(workarea: &mut Workarea) {
+    while let Some((is_details, line)) = workarea.containers.pop() {
+        eprintln!(
+            "{}:{}: container without closing :::",
+            workarea.source_name(),
+            line
+        );
+        workarea.push_event(&Event::Html(close(is_details).into()));
+    }
+}
+
+fn close(is_details: bool) -> String {
+    if is_details {
+        "</details>\n".to_string()
+    } else {
+        "</div>\n".to_string()
+    }
+}
+
+/// the text of the first line, and the number of its events including the line break.
+/// `None` when the line has other than text (e.g. emphasis).
+fn first_line(events: &[Event]) -> Option<(String, usize)> {
+    let mut line = String::new();
+    for (i, ev) in events.iter().enumerate() {
+        match ev {
+            Event::Text(s) => line.push_str(s),
+            Event::SoftBreak | Event::HardBreak => return Some((line, i + 1)),
+            _ if i == 0 => return None,
+            _ => return Some((line, i)),
+        }
+    }
+
+    Some((line, events.len()))
+}
+
+/// same as `first_line`, but the last line including the line break before it
+fn last_line(events: &[Event]) -> Option<(String, usize)> {
+    let mut texts = vec![];
+    for (i, ev) in events.iter().rev().enumerate() {
+        match ev {
+            Event::Text(s) => texts.push(s.as_ref()),
+            Event::SoftBreak | Event::HardBreak => {
+                texts.reverse();
+                return Some((texts.concat(), i + 1));
+            }
+            _ => return None,
+        }
+    }
+
+    texts.reverse();
+    Some((texts.concat(), events.len()))
+}
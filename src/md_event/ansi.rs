@@ -0,0 +1,320 @@
+use pulldown_cmark::escape::escape_html;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// lines starting with these are commands in a `console` code block
+const PROMPTS: [&str; 2] = ["$ ", "# "];
+
+/// the 16 colors of xterm
+const PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+type Color = (u8, u8, u8);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Style {
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    fg: Option<Color>,
+    bg: Option<Color>,
+}
+
+impl Style {
+    /// applies the parameters of a SGR sequence (e.g. `1;38;5;208` of `ESC[1;38;5;208m`)
+    fn apply(&mut self, params: &str) {
+        let params = params
+            .split(';')
+            .map(|p| p.parse::<u16>().unwrap_or(0))
+            .collect::<Vec<u16>>();
+
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                9 => self.strikethrough = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                29 => self.strikethrough = false,
+                n @ 30..=37 => self.fg = Some(PALETTE[(n - 30) as usize]),
+                n @ 90..=97 => self.fg = Some(PALETTE[(n - 90 + 8) as usize]),
+                39 => self.fg = None,
+                n @ 40..=47 => self.bg = Some(PALETTE[(n - 40) as usize]),
+                n @ 100..=107 => self.bg = Some(PALETTE[(n - 100 + 8) as usize]),
+                49 => self.bg = None,
+                n @ (38 | 48) => {
+                    let (color, used) = extended_color(&params[i + 1..]);
+                    if n == 38 {
+                        self.fg = color;
+                    } else {
+                        self.bg = color;
+                    }
+                    i += used;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    fn css(&self) -> String {
+        let mut css = vec![];
+        if let Some((r, g, b)) = self.fg {
+            css.push(format!("color:#{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if let Some((r, g, b)) = self.bg {
+            css.push(format!("background-color:#{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if self.dim {
+            css.push("opacity:0.7".to_string());
+        }
+        if self.italic {
+            css.push("font-style:italic".to_string());
+        }
+        let decorations = [
+            (self.underline, "underline"),
+            (self.strikethrough, "line-through"),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, d)| *d)
+        .collect::<Vec<&str>>();
+        if !decorations.is_empty() {
+            css.push(format!("text-decoration:{}", decorations.join(" ")));
+        }
+        css.join(";")
+    }
+}
+
+/// `5;n` (256 colors) or `2;r;g;b` (truecolor) after 38 or 48,
+/// returns the color and the number of the parameters used
+fn extended_color(params: &[u16]) -> (Option<Color>, usize) {
+    match params {
+        [5, n, ..] => (Some(color_256(*n as u8)), 2),
+        [2, r, g, b, ..] => (Some((*r as u8, *g as u8, *b as u8)), 4),
+        _ => (None, params.len()),
+    }
+}
+
+fn color_256(n: u8) -> Color {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match n {
+        0..=15 => PALETTE[n as usize],
+        16..=231 => {
+            let n = n - 16;
+            (
+                LEVELS[(n / 36) as usize],
+                LEVELS[(n / 6 % 6) as usize],
+                LEVELS[(n % 6) as usize],
+            )
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// converts text with escape sequences into HTML, keeping the style between calls
+#[derive(Default)]
+struct Renderer {
+    style: Style,
+}
+
+impl Renderer {
+    fn render(&mut self, text: &str, html: &mut String) {
+        let mut rest = text;
+        while !rest.is_empty() {
+            let end = rest.find(ESC).unwrap_or(rest.len());
+            self.push_text(&rest[..end], html);
+            rest = &rest[end..];
+
+            if !rest.is_empty() {
+                rest = self.escape_sequence(rest);
+            }
+        }
+    }
+
+    fn push_text(&self, text: &str, html: &mut String) {
+        if text.is_empty() {
+            return;
+        }
+
+        let css = self.style.css();
+        if css.is_empty() {
+            escape_html(&mut *html, text).unwrap();
+        } else {
+            html.push_str(r#"<span style=""#);
+            html.push_str(&css);
+            html.push_str(r#"">"#);
+            escape_html(&mut *html, text).unwrap();
+            html.push_str("</span>");
+        }
+    }
+
+    /// consumes an escape sequence at the beginning of `s`, only SGR changes the style
+    fn escape_sequence<'a>(&mut self, s: &'a str) -> &'a str {
+        let s = &s[ESC.len_utf8()..];
+
+        if let Some(csi) = s.strip_prefix('[') {
+            // CSI: parameters and intermediates, then a final byte in '@'..='~'
+            return match csi.find(|c| ('@'..='~').contains(&c)) {
+                Some(i) => {
+                    if csi[i..].starts_with('m') {
+                        self.style.apply(&csi[..i]);
+                    }
+                    &csi[i + 1..]
+                }
+                None => "",
+            };
+        }
+
+        if let Some(osc) = s.strip_prefix(']') {
+            // OSC (e.g. hyperlinks): terminated by BEL or ESC '\'
+            return match osc.find([BEL, ESC]) {
+                Some(i) if osc[i..].starts_with(BEL) => &osc[i + 1..],
+                Some(i) => osc[i + 1..].strip_prefix('\\').unwrap_or(&osc[i + 1..]),
+                None => "",
+            };
+        }
+
+        // other sequences (e.g. `ESC ( B`): intermediates in ' '..='/', then a final character
+        let s = s.trim_start_matches(|c| (' '..='/').contains(&c));
+        let mut chars = s.chars();
+        chars.next();
+        chars.as_str()
+    }
+}
+
+/// converts SGR escape sequences (colors, bold, underline, ...) of an `ansi` code block
+/// into styled `<span>`s, other escape sequences are removed
+pub fn to_html(code: &str) -> String {
+    let mut html = String::with_capacity(code.len());
+    Renderer::default().render(code, &mut html);
+    html
+}
+
+/// same as `to_html`, but the lines starting with a prompt (`$ ` or `# `) of a `console`
+/// code block are marked up as commands, and the others as their output
+pub fn console_to_html(code: &str) -> String {
+    let mut html = String::with_capacity(code.len());
+    let mut renderer = Renderer::default();
+
+    for line in code.split_inclusive('\n') {
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(l) => (l, "\n"),
+            None => (line, ""),
+        };
+
+        match PROMPTS.iter().find(|p| line.starts_with(*p)) {
+            Some(prompt) => {
+                html.push_str(r#"<span class="prompt">"#);
+                escape_html(&mut html, prompt).unwrap();
+                html.push_str(r#"</span><span class="command">"#);
+                escape_html(&mut html, &line[prompt.len()..]).unwrap();
+                html.push_str("</span>");
+            }
+            None => {
+                html.push_str(r#"<span class="output">"#);
+                renderer.render(line, &mut html);
+                html.push_str("</span>");
+            }
+        }
+        html.push_str(newline);
+    }
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{color_256, console_to_html, to_html};
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(to_html("テスト <a> & 🚿\n"), "テスト &lt;a&gt; &amp; 🚿\n");
+    }
+
+    #[test]
+    fn test_16_colors() {
+        assert_eq!(
+            to_html("\x1b[31merror\x1b[0m: \x1b[1;92mok\x1b[m\n"),
+            "<span style=\"color:#cd0000\">error</span>: <span style=\"color:#00ff00;font-weight:bold\">ok</span>\n"
+        );
+        assert_eq!(
+            to_html("\x1b[44;4m𩸽\x1b[24m𩸽\x1b[49m"),
+            "<span style=\"background-color:#0000ee;text-decoration:underline\">𩸽</span><span style=\"background-color:#0000ee\">𩸽</span>"
+        );
+    }
+
+    #[test]
+    fn test_256_colors() {
+        assert_eq!(color_256(9), (255, 0, 0));
+        assert_eq!(color_256(208), (255, 135, 0));
+        assert_eq!(color_256(244), (128, 128, 128));
+        assert_eq!(
+            to_html("\x1b[38;5;208mwarning\x1b[39m"),
+            "<span style=\"color:#ff8700\">warning</span>"
+        );
+    }
+
+    #[test]
+    fn test_truecolor() {
+        assert_eq!(
+            to_html("\x1b[38;2;1;2;3;48;2;255;255;255mx\x1b[0m"),
+            "<span style=\"color:#010203;background-color:#ffffff\">x</span>"
+        );
+    }
+
+    #[test]
+    fn test_other_sequences_are_removed() {
+        assert_eq!(to_html("a\x1b[2Kb\x1b[1Ac\x1b(Bd"), "abcd");
+        assert_eq!(
+            to_html("\x1b]8;;https://example.com\x07link\x1b]8;;\x1b\\"),
+            "link"
+        );
+        assert_eq!(to_html("broken\x1b[31"), "broken");
+    }
+
+    #[test]
+    fn test_console() {
+        let code = "$ cargo build\n\x1b[32m   Compiling\x1b[0m mtoh\n# id -u\n0\n";
+        assert_eq!(
+            console_to_html(code),
+            "<span class=\"prompt\">$ </span><span class=\"command\">cargo build</span>\n\
+             <span class=\"output\"><span style=\"color:#00cd00\">   Compiling</span> mtoh</span>\n\
+             <span class=\"prompt\"># </span><span class=\"command\">id -u</span>\n\
+             <span class=\"output\">0</span>\n"
+        );
+    }
+}
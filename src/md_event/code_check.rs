@@ -0,0 +1,114 @@
+use serde::Deserialize;
+
+use super::code_info::CodeInfo;
+use super::workarea::Workarea;
+use crate::settings::settings::Settings;
+
+/// reports syntax errors of `json`, `toml` and `yaml` code blocks,
+/// unless the block has the `nocheck` attribute
+pub fn check(workarea: &Workarea, settings: &Settings, info: &CodeInfo) {
+    if !settings.code.check.enabled || info.has("nocheck") {
+        return;
+    }
+
+    let code = workarea.contents.join("");
+    let Err((line, message)) = validate(&info.lang, &code) else {
+        return;
+    };
+
+    // the code starts at the next line of the fence, unless it is loaded from another file
+    let line = match workarea.line() {
+        0 => 0,
+        n if info.has("include") => n,
+        n => n + line,
+    };
+
+    eprintln!(
+        "{}:{}: invalid {} code block: {}",
+        workarea.source_name(),
+        line,
+        info.lang,
+        message
+    );
+}
+
+/// returns the 1-based line in the code and the message of the syntax error
+fn validate(lang: &str, code: &str) -> Result<(), (usize, String)> {
+    match lang {
+        "json" => match serde_json::from_str::<serde_json::Value>(code) {
+            Ok(_) => Ok(()),
+            Err(e) => Err((
+                e.line(),
+                strip_location(e.to_string(), e.line(), e.column()),
+            )),
+        },
+        "toml" => match toml::from_str::<toml::Value>(code) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let (line, column) = match e.line_col() {
+                    Some((l, c)) => (l + 1, c + 1),
+                    None => (1, 1),
+                };
+                Err((line, strip_location(e.to_string(), line, column)))
+            }
+        },
+        "yaml" | "yml" => {
+            for document in serde_yaml::Deserializer::from_str(code) {
+                if let Err(e) = serde_yaml::Value::deserialize(document) {
+                    let (line, column) = match e.location() {
+                        Some(l) => (l.line(), l.column()),
+                        None => (1, 1),
+                    };
+                    return Err((line, strip_location(e.to_string(), line, column)));
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// the line in the message is relative to the code block, so it is removed
+fn strip_location(message: String, line: usize, column: usize) -> String {
+    let location = format!(" at line {} column {}", line, column);
+    match message.find(&location) {
+        Some(i) => [&message[..i], &message[i + location.len()..]].join(""),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+
+    #[test]
+    fn test_validate_json() {
+        assert!(validate("json", "{\"a\": [1, 2]}\n").is_ok());
+
+        let (line, message) = validate("json", "{\n  \"a\": 1\n  \"b\": 2\n}\n").unwrap_err();
+        assert_eq!(line, 3);
+        assert!(!message.contains("line"), "{}", message);
+    }
+
+    #[test]
+    fn test_validate_toml() {
+        assert!(validate("toml", "[a]\nb = \"テスト\"\n").is_ok());
+
+        let (line, _) = validate("toml", "[a]\nb = 1\nc = \n").unwrap_err();
+        assert_eq!(line, 3);
+    }
+
+    #[test]
+    fn test_validate_yaml() {
+        assert!(validate("yaml", "a:\n  - 1\n  - 2\n---\nb: 🚿\n").is_ok());
+
+        let (line, _) = validate("yml", "a: 1\n---\nb: [1, 2\n").unwrap_err();
+        assert_eq!(line, 4);
+    }
+
+    #[test]
+    fn test_validate_unknown_language() {
+        assert!(validate("rust", "fn main( {").is_ok());
+        assert!(validate("", "{").is_ok());
+    }
+}
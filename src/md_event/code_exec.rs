@@ -0,0 +1,304 @@
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::{
+    fs::{self, File},
+    hash::Hasher,
+    io::{Read, Write},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use fnv::FnvHasher;
+use pulldown_cmark::escape::escape_html;
+use serde::{Deserialize, Serialize};
+
+use super::code_info::CodeInfo;
+use super::workarea::Workarea;
+use crate::settings::settings::Settings;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct ExecOutput {
+    stdout: String,
+    stderr: String,
+    /// `None` when the command was killed by a signal
+    status: Option<i32>,
+}
+
+/// runs the code block with the `exec` attribute by the interpreter of its language,
+/// and returns the output as HTML.
+/// the output is cached by the command and the code, so unchanged blocks are not run again.
+pub fn run(workarea: &Workarea, settings: &Settings, info: &CodeInfo) -> Option<String> {
+    let exec = &settings.code.exec;
+    if !exec.enabled || !info.has("exec") {
+        return None;
+    }
+
+    let location = format!("{}:{}", workarea.source_name(), workarea.line());
+    let Some(command) = exec.interpreters.get(&info.lang).filter(|c| !c.is_empty()) else {
+        eprintln!(
+            "{}: no interpreter for \"{}\" in code.exec.interpreters",
+            location, info.lang
+        );
+        return None;
+    };
+
+    let code = workarea.contents.join("");
+    let cache_file =
+        Path::new(&exec.cache_dir).join(format!("{:016x}.json", cache_key(command, &code)));
+
+    let output = match load_cache(&cache_file) {
+        Some(o) => o,
+        None => match execute(command, &code, Duration::from_secs(exec.timeout)) {
+            Ok(o) => {
+                if let Err(e) = save_cache(&cache_file, &o) {
+                    eprintln!("{}: {}", cache_file.to_string_lossy(), e);
+                }
+                o
+            }
+            Err(e) => {
+                eprintln!("{}: {}", location, e);
+                return None;
+            }
+        },
+    };
+
+    if output.status != Some(0) {
+        let status = match output.status {
+            Some(s) => s.to_string(),
+            None => "a signal".to_string(),
+        };
+        eprintln!("{}: {} exited with {}", location, command[0], status);
+    }
+
+    Some(render(&output))
+}
+
+/// FNV-1a of the interpreter command and the code. the cache files are kept on disk,
+/// so the key must not change over the Rust versions like `DefaultHasher`
+fn cache_key(command: &[String], code: &str) -> u64 {
+    let mut hasher = FnvHasher::default();
+    // the lengths keep `["sh", "-c"]` and `["sh -", "c"]` apart
+    for s in command.iter().map(|s| s.as_str()).chain([code]) {
+        hasher.write(&(s.len() as u64).to_le_bytes());
+        hasher.write(s.as_bytes());
+    }
+    hasher.finish()
+}
+
+fn load_cache(cache_file: &Path) -> Option<ExecOutput> {
+    let s = fs::read_to_string(cache_file).ok()?;
+    serde_json::from_str(&s).ok()
+}
+
+fn save_cache(cache_file: &Path, output: &ExecOutput) -> Result<(), String> {
+    if let Some(parent) = cache_file.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let json = serde_json::to_string(output).map_err(|e| e.to_string())?;
+    let mut file = File::create(cache_file).map_err(|e| e.to_string())?;
+    file.write_all(json.as_bytes()).map_err(|e| e.to_string())
+}
+
+fn read_all<R: Read + Send + 'static>(mut reader: R) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = vec![];
+        let _ = reader.read_to_end(&mut buf);
+        let _ = sender.send(String::from_utf8_lossy(&buf).to_string());
+    });
+    receiver
+}
+
+/// kills the command and the processes started by it (e.g. `sleep 999 &`),
+/// which would keep the pipes open
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // the process group has the id of the command, see `execute`
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// gives the code to the stdin of the command, and kills it after `timeout`
+fn execute(command: &[String], code: &str, timeout: Duration) -> Result<ExecOutput, String> {
+    let mut builder = Command::new(&command[0]);
+    builder
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // a new process group, which is killed with the background processes of the command
+    #[cfg(unix)]
+    builder.process_group(0);
+
+    let mut child = match builder.spawn() {
+        Ok(c) => c,
+        Err(e) => return Err(format!("cannot execute {}: {}", command[0], e)),
+    };
+
+    // the pipes are served by threads, so a command filling one of them does not block
+    let mut stdin = child.stdin.take().unwrap();
+    let code = code.to_string();
+    thread::spawn(move || {
+        // the command may exit without reading all of the code
+        let _ = stdin.write_all(code.as_bytes());
+    });
+    let stdout = read_all(child.stdout.take().unwrap());
+    let stderr = read_all(child.stderr.take().unwrap());
+
+    let timed_out = || {
+        format!(
+            "{} timed out after {} seconds",
+            command[0],
+            timeout.as_secs()
+        )
+    };
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(s)) => break s,
+            Ok(None) if Instant::now() >= deadline => {
+                kill(&mut child);
+                return Err(timed_out());
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                kill(&mut child);
+                return Err(format!("{}: {}", command[0], e));
+            }
+        }
+    };
+    kill(&mut child);
+
+    // the pipes may still be open by the processes out of the process group
+    let receive = |receiver: Receiver<String>| {
+        receiver
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .map_err(|_| timed_out())
+    };
+
+    Ok(ExecOutput {
+        stdout: receive(stdout)?,
+        stderr: receive(stderr)?,
+        status: status.code(),
+    })
+}
+
+fn render(output: &ExecOutput) -> String {
+    let mut html = String::from(r#"<pre class="exec-output"><code>"#);
+    escape_html(&mut html, &output.stdout).unwrap();
+    if !output.stderr.is_empty() {
+        html.push_str(r#"<span class="exec-stderr">"#);
+        escape_html(&mut html, &output.stderr).unwrap();
+        html.push_str("</span>");
+    }
+    html.push_str("</code></pre>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{cache_key, execute, render, ExecOutput};
+
+    fn sh() -> Vec<String> {
+        vec!["sh".to_string()]
+    }
+
+    #[test]
+    fn test_execute() {
+        let output = execute(
+            &sh(),
+            "echo 'テスト 🚿'\necho error >&2\nexit 3\n",
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            ExecOutput {
+                stdout: "テスト 🚿\n".to_string(),
+                stderr: "error\n".to_string(),
+                status: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_execute_timeout() {
+        let result = execute(&sh(), "sleep 5\n", Duration::from_secs(1));
+        assert_eq!(result, Err("sh timed out after 1 seconds".to_string()));
+    }
+
+    #[test]
+    fn test_execute_background() {
+        let started = Instant::now();
+        let output = execute(
+            &sh(),
+            "echo start\nsleep 999 &\necho end\n",
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert_eq!(output.stdout, "start\nend\n");
+        assert_eq!(output.status, Some(0));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let command = vec!["sh".to_string(), "-c".to_string()];
+        assert_eq!(
+            cache_key(&command, "echo 🚿"),
+            cache_key(&command, "echo 🚿")
+        );
+        assert_ne!(cache_key(&command, "echo 🚿"), cache_key(&sh(), "echo 🚿"));
+        assert_ne!(
+            cache_key(&command, "echo 🚿"),
+            cache_key(&["sh -".to_string(), "c".to_string()], "echo 🚿")
+        );
+        // stable over the builds, the cache files are named by it
+        assert_eq!(cache_key(&sh(), ""), 0x42c2c09dace2dd9c);
+    }
+
+    #[test]
+    fn test_execute_not_found() {
+        let command = vec!["mtoh-no-such-command".to_string()];
+        assert!(execute(&command, "", Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let output = ExecOutput {
+            stdout: "<b>&</b>\n".to_string(),
+            stderr: "".to_string(),
+            status: Some(0),
+        };
+        assert_eq!(
+            render(&output),
+            "<pre class=\"exec-output\"><code>&lt;b&gt;&amp;&lt;/b&gt;\n</code></pre>\n"
+        );
+
+        let output = ExecOutput {
+            stdout: "".to_string(),
+            stderr: "𩸽\n".to_string(),
+            status: Some(1),
+        };
+        assert_eq!(
+            render(&output),
+            "<pre class=\"exec-output\"><code><span class=\"exec-stderr\">𩸽\n</span></code></pre>\n"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::{fs, ops::RangeInclusive, sync::OnceLock};
+
+use regex::Regex;
+
+use super::code_info::CodeInfo;
+use super::workarea::Workarea;
+
+const REGION_COMMENT: &str = r"^\s*(?://+|#+|--|;+|/\*|<!--)\s*";
+
+/// `// region: name`, compiled once
+fn re_region_begin() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        let begin = format!(
+            r"{}region:\s*(?P<name>.*?)\s*(?:\*/|-->)?\s*$",
+            REGION_COMMENT
+        );
+        let Ok(re) = Regex::new(&begin) else {
+            panic!("regex compile failed: {}", begin);
+        };
+        re
+    })
+}
+
+/// `// region: name` or `// endregion`, compiled once
+fn re_region_marker() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        let Ok(re) = Regex::new(&format!(r"{}(?:end)?region\b", REGION_COMMENT)) else {
+            panic!("regex compile failed: {}", REGION_COMMENT);
+        };
+        re
+    })
+}
+
+/// replaces the code with the file of the `include` attribute,
+/// optionally limited by `lines="10-40"` or `region="setup"`
+pub fn load(workarea: &mut Workarea, info: &CodeInfo) {
+    let Some(target) = info.get("include") else {
+        return;
+    };
+
+    let path = workarea.base_dir().join(target);
+    let text = match fs::read_to_string(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!(
+                "{}: cannot include {}: {}",
+                workarea.source_name(),
+                path.to_string_lossy(),
+                e
+            );
+            return;
+        }
+    };
+
+    let code = if let Some(lines) = info.get("lines") {
+        select_lines(&text, lines)
+    } else if let Some(region) = info.get("region") {
+        select_region(&text, region)
+    } else {
+        Ok(text)
+    };
+
+    match code {
+        Ok(c) => {
+            workarea.clear_content();
+            workarea.push_content(&c);
+        }
+        Err(e) => eprintln!(
+            "{}: {}: {}",
+            workarea.source_name(),
+            path.to_string_lossy(),
+            e
+        ),
+    }
+}
+
+/// parses `"1-3,8,10-"` into 1-based ranges
+fn parse_lines(lines: &str) -> Result<Vec<RangeInclusive<usize>>, String> {
+    let number = |s: &str, default: usize| match s.trim() {
+        "" => Ok(default),
+        n => n
+            .parse::<usize>()
+            .map_err(|_| format!("invalid line range: {}", lines)),
+    };
+
+    lines
+        .split(',')
+        .map(|r| match r.split_once('-') {
+            Some((from, to)) => Ok(number(from, 1)?..=number(to, usize::MAX)?),
+            None => {
+                let n = number(r, 0)?;
+                Ok(n..=n)
+            }
+        })
+        .collect()
+}
+
+fn select_lines(text: &str, lines: &str) -> Result<String, String> {
+    let ranges = parse_lines(lines)?;
+
+    let mut result = String::new();
+    for range in ranges.iter() {
+        for (_, line) in text
+            .split_inclusive('\n')
+            .enumerate()
+            .filter(|(i, _)| range.contains(&(i + 1)))
+        {
+            result.push_str(line);
+        }
+    }
+
+    if result.is_empty() {
+        return Err(format!("no lines in {}", lines));
+    }
+
+    Ok(ensure_newline(result))
+}
+
+/// lines between `// region: name` and `// endregion` (the markers of any other region are removed)
+fn select_region(text: &str, name: &str) -> Result<String, String> {
+    let mut lines: Vec<&str> = vec![];
+    let mut depth = 0;
+    let mut found = false;
+    for line in text.split_inclusive('\n') {
+        if depth == 0 {
+            if re_region_begin()
+                .captures(line.trim_end())
+                .is_some_and(|c| &c["name"] == name)
+            {
+                depth = 1;
+                found = true;
+            }
+            continue;
+        }
+
+        if re_region_marker().is_match(line) {
+            if line
+                .trim_start_matches(|c: char| !c.is_alphabetic())
+                .starts_with("endregion")
+            {
+                depth -= 1;
+            } else {
+                depth += 1;
+            }
+            continue;
+        }
+
+        lines.push(line);
+    }
+
+    if !found {
+        return Err(format!("region \"{}\" not found", name));
+    }
+
+    Ok(ensure_newline(dedent(&lines)))
+}
+
+/// removes the common indent of spaces and tabs
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|l| {
+            if l.trim().is_empty() {
+                l.trim_start_matches([' ', '\t'])
+            } else {
+                &l[indent..]
+            }
+        })
+        .collect()
+}
+
+fn ensure_newline(mut code: String) -> String {
+    if !code.ends_with('\n') {
+        code.push('\n');
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dedent, select_lines, select_region};
+
+    const SOURCE: &str = r#"use std::fs;
+
+fn main() {
+    // region: setup
+    let a = 1;
+    // region: inner
+    let b = 2;
+    // endregion: inner
+
+    let c = 3;
+    // endregion: setup
+    println!("{}", a + b + c);
+}
+"#;
+
+    #[test]
+    fn test_select_lines() {
+        assert_eq!(select_lines(SOURCE, "1").unwrap(), "use std::fs;\n");
+        assert_eq!(
+            select_lines(SOURCE, "3-4,12").unwrap(),
+            "fn main() {\n    // region: setup\n    println!(\"{}\", a + b + c);\n"
+        );
+        assert_eq!(select_lines(SOURCE, "13-").unwrap(), "}\n");
+        assert!(select_lines(SOURCE, "100-").is_err());
+        assert!(select_lines(SOURCE, "a-b").is_err());
+    }
+
+    #[test]
+    fn test_select_region() {
+        assert_eq!(
+            select_region(SOURCE, "setup").unwrap(),
+            "let a = 1;\nlet b = 2;\n\nlet c = 3;\n"
+        );
+        assert_eq!(select_region(SOURCE, "inner").unwrap(), "let b = 2;\n");
+        assert!(select_region(SOURCE, "unknown").is_err());
+
+        let python = "# region: 設定\nx = 1\n# endregion\n";
+        assert_eq!(select_region(python, "設定").unwrap(), "x = 1\n");
+    }
+
+    #[test]
+    fn test_dedent() {
+        assert_eq!(dedent(&["  a\n", "\n", "    b\n"]), "a\n\n  b\n");
+        // an ideographic space is not an indent
+        assert_eq!(dedent(&["\u{3000}a\n", " b\n"]), "\u{3000}a\n b\n");
+        assert_eq!(dedent(&["\t\u{3000}a\n", "\t b\n"]), "\u{3000}a\n b\n");
+    }
+}
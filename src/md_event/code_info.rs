@@ -0,0 +1,103 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+const ATTRIBUTE: &str =
+    r#"(?P<key>[\w-]+)(?:\s*=\s*(?:"(?P<quoted>[^"]*)"|'(?P<single>[^']*)'|(?P<bare>[^\s,}]+)))?"#;
+
+/// `ATTRIBUTE`, compiled once since every code block has an info string
+fn re_attribute() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        let Ok(re) = Regex::new(ATTRIBUTE) else {
+            panic!("regex compile failed: {}", ATTRIBUTE);
+        };
+        re
+    })
+}
+
+/// info string of a fenced code block,
+/// e.g. `rust {include="../src/lib.rs", lines="10-40"}` or `sh exec`
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct CodeInfo {
+    pub lang: String,
+    /// attributes in order of appearance, the value is `None` for a flag (e.g. `exec`)
+    pub attrs: Vec<(String, Option<String>)>,
+}
+
+impl CodeInfo {
+    pub fn parse(info: &str) -> Self {
+        let info = info.trim();
+        let lang_end = info
+            .find(|c: char| c.is_whitespace() || c == '{')
+            .unwrap_or(info.len());
+        let lang = info[..lang_end].to_string();
+
+        let attrs = re_attribute()
+            .captures_iter(&info[lang_end..])
+            .map(|c| {
+                let value = c
+                    .name("quoted")
+                    .or_else(|| c.name("single"))
+                    .or_else(|| c.name("bare"))
+                    .map(|m| m.as_str().to_string());
+                (c["key"].to_string(), value)
+            })
+            .collect::<Vec<(String, Option<String>)>>();
+
+        Self { lang, attrs }
+    }
+
+    /// value of the attribute `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// whether the attribute `key` is given, as a flag or with a value
+    pub fn has(&self, key: &str) -> bool {
+        self.attrs.iter().any(|(k, _)| k == key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CodeInfo;
+
+    #[test]
+    fn test_lang_only() {
+        let info = CodeInfo::parse("rust");
+        assert_eq!(info.lang, "rust");
+        assert!(info.attrs.is_empty());
+
+        let info = CodeInfo::parse("");
+        assert_eq!(info.lang, "");
+        assert!(info.attrs.is_empty());
+    }
+
+    #[test]
+    fn test_braced_attributes() {
+        let info = CodeInfo::parse(r#"rust {include="../src/lib.rs", lines="10-40"}"#);
+        assert_eq!(info.lang, "rust");
+        assert_eq!(info.get("include"), Some("../src/lib.rs"));
+        assert_eq!(info.get("lines"), Some("10-40"));
+        assert_eq!(info.get("region"), None);
+
+        let info = CodeInfo::parse(r#"python{region='setup' nocheck}"#);
+        assert_eq!(info.lang, "python");
+        assert_eq!(info.get("region"), Some("setup"));
+        assert!(info.has("nocheck"));
+        assert_eq!(info.get("nocheck"), None);
+    }
+
+    #[test]
+    fn test_bare_attributes() {
+        let info = CodeInfo::parse(r#"sh exec title="インストール 手順" file=out/run.sh"#);
+        assert_eq!(info.lang, "sh");
+        assert!(info.has("exec"));
+        assert_eq!(info.get("title"), Some("インストール 手順"));
+        assert_eq!(info.get("file"), Some("out/run.sh"));
+    }
+}
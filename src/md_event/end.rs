@@ -1,9 +1,12 @@
+use super::ansi;
+use super::code_check;
+use super::code_exec;
+use super::code_include;
+use super::code_info::CodeInfo;
+use super::highlight;
 use super::workarea::Workarea;
 use crate::settings::settings::Settings;
 use pulldown_cmark::{CodeBlockKind, Event};
-use syntect::highlighting::ThemeSet;
-use syntect::html::highlighted_html_for_string;
-use syntect::parsing::SyntaxSet;
 
 pub fn event_code<'a>(
     workarea: &mut Workarea<'a>,
@@ -11,41 +14,37 @@ pub fn event_code<'a>(
     ev: &Event<'a>,
     kind: &CodeBlockKind,
 ) {
-    let lang = match &kind {
-        CodeBlockKind::Fenced(f) => f.to_string(),
-        _ => return,
+    let mut info = match &kind {
+        CodeBlockKind::Fenced(f) => CodeInfo::parse(f),
+        CodeBlockKind::Indented => CodeInfo::default(),
     };
+    if info.lang.is_empty() {
+        info.lang = settings.code.highlight.default_language.to_owned();
+    }
+    let lang = &info.lang;
+
+    code_include::load(workarea, &info);
+    code_check::check(workarea, settings, &info);
+    let exec_output = code_exec::run(workarea, settings, &info);
 
     // println!("{}", l);
     let code = workarea.contents.join("");
 
-    // Load these once at the start of your program
-    let ps = SyntaxSet::load_defaults_newlines();
-    let ts = ThemeSet::load_defaults();
-
-    let language = match lang.as_str() {
-        "rust" => "Rust",
-        "python" => "Python",
-        "cpp" => "C++",
-        _ => "Plain Text",
-    };
-
-    let syntax = match ps.find_syntax_by_name(&language) {
-        Some(s) => s,
-        None => {
-            eprintln!("{} not found.", lang);
-            ps.find_syntax_by_name("Plain Text").unwrap()
+    let t = match lang.as_str() {
+        "" => {
+            // not highlighted, only escaped
+            workarea.clear_content();
+            workarea.push_event(&Event::Text(code.into()));
+            workarea.push_event(ev);
+            return;
         }
+        "ansi" => ansi::to_html(&code),
+        "console" => ansi::console_to_html(&code),
+        "diff" => highlight::diff(&code, "", settings),
+        l if l.starts_with("diff-") => highlight::diff(&code, &l["diff-".len()..], settings),
+        _ if info.has("diff") => highlight::diff(&code, lang, settings),
+        _ => highlight::block(&code, lang, settings),
     };
-
-    let hh = highlighted_html_for_string(
-        &code,
-        &ps,
-        &syntax,
-        &ts.themes[&settings.code.highlight.theme],
-    );
-
-    let t = hh.unwrap();
     // t.push_str(r#"</code></pre>"#);
 
     // cleanup
@@ -56,4 +55,23 @@ pub fn event_code<'a>(
     // return Event::Html(t.into());
     workarea.push_event(&Event::Html(t.into()));
     workarea.push_event(ev);
+    if let Some(output) = exec_output {
+        workarea.push_event(&Event::Html(output.into()));
+    }
+}
+
+/// keeps the code block with the `file` attribute for `mtoh tangle`,
+/// the code is not highlighted, checked nor run
+pub fn tangle(workarea: &mut Workarea, kind: &CodeBlockKind) {
+    if let CodeBlockKind::Fenced(f) = kind {
+        let info = CodeInfo::parse(f);
+        if let Some(file) = info.get("file") {
+            code_include::load(workarea, &info);
+            workarea
+                .tangles
+                .push((file.to_string(), workarea.contents.join("")));
+        }
+    }
+
+    workarea.clear_content();
 }
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::settings::settings::Settings;
+
+const SIDENOTE: &str = "sidenote";
+const POPOVER: &str = "popover";
+
+/// a footnote definition and the references to it
+struct Note<'a> {
+    number: usize,
+    events: Vec<Event<'a>>,
+    references: usize,
+}
+
+/// the footnotes of a page, numbered in the order of their first references
+struct Footnotes<'a> {
+    /// the definitions not referenced yet, by label
+    definitions: HashMap<String, Vec<Event<'a>>>,
+    notes: Vec<Note<'a>>,
+    /// index of `notes` by label
+    numbers: HashMap<String, usize>,
+}
+
+/// replaces the footnote references and definitions in `events`.
+/// the definitions are listed at the end with links back to the references,
+/// or written next to the first reference in the sidenote and popover styles.
+/// definitions without any reference are dropped.
+pub fn collect<'a>(events: &mut Vec<Event<'a>>, settings: &Settings) {
+    let definitions = take_definitions(events);
+    if definitions.is_empty()
+        && !events
+            .iter()
+            .any(|e| matches!(e, Event::FootnoteReference(_)))
+    {
+        return;
+    }
+
+    let mut footnotes = Footnotes {
+        definitions,
+        notes: vec![],
+        numbers: HashMap::new(),
+    };
+    *events = replace_references(&mut footnotes, std::mem::take(events), settings);
+
+    if matches!(settings.footnote.style.as_str(), SIDENOTE | POPOVER) {
+        return;
+    }
+
+    // the references in the notes add the notes they refer to at the end
+    let mut i = 0;
+    while i < footnotes.notes.len() {
+        let note_events = std::mem::take(&mut footnotes.notes[i].events);
+        footnotes.notes[i].events = replace_references(&mut footnotes, note_events, settings);
+        i += 1;
+    }
+
+    let notes = footnotes.notes;
+    if notes.is_empty() {
+        return;
+    }
+
+    events.push(Event::Html("<section class=\"footnotes\">\n<ol>\n".into()));
+    for note in notes.into_iter() {
+        events.push(Event::Html(
+            format!("<li id=\"fn-{}\">\n", note.number).into(),
+        ));
+
+        let backrefs = (1..=note.references)
+            .map(|n| {
+                format!(
+                    r##" <a href="#{}" class="footnote-backref">↩{}</a>"##,
+                    reference_id(note.number, n),
+                    if n > 1 {
+                        format!("<sup>{}</sup>", n)
+                    } else {
+                        String::new()
+                    }
+                )
+            })
+            .collect::<String>();
+
+        // the links back are put at the end of the last paragraph
+        let mut note_events = note.events;
+        match note_events.last() {
+            Some(Event::End(Tag::Paragraph)) => {
+                note_events.insert(note_events.len() - 1, Event::Html(backrefs.into()));
+            }
+            _ => note_events.push(Event::Html(backrefs.into())),
+        }
+        events.extend(note_events);
+
+        events.push(Event::Html("</li>\n".into()));
+    }
+    events.push(Event::Html("</ol>\n</section>\n".into()));
+}
+
+/// replaces the footnote references in `events` with the links to the notes.
+/// in the sidenote and popover styles, the note is written at its first reference.
+fn replace_references<'a>(
+    footnotes: &mut Footnotes<'a>,
+    events: Vec<Event<'a>>,
+    settings: &Settings,
+) -> Vec<Event<'a>> {
+    let inline = matches!(settings.footnote.style.as_str(), SIDENOTE | POPOVER);
+    let mut result = vec![];
+
+    for ev in events.into_iter() {
+        let Event::FootnoteReference(label) = ev else {
+            result.push(ev);
+            continue;
+        };
+
+        let index = match footnotes.numbers.get(label.as_ref()) {
+            Some(i) => *i,
+            None => {
+                let Some(definition) = footnotes.definitions.remove(label.as_ref()) else {
+                    result.push(Event::Text(format!("[^{}]", label).into()));
+                    continue;
+                };
+                let index = footnotes.notes.len();
+                footnotes.numbers.insert(label.to_string(), index);
+                footnotes.notes.push(Note {
+                    number: index + 1,
+                    events: definition,
+                    references: 0,
+                });
+                index
+            }
+        };
+
+        let note = &mut footnotes.notes[index];
+        note.references += 1;
+        let number = note.number;
+        let id = reference_id(number, note.references);
+
+        if !inline || note.references > 1 {
+            result.push(Event::Html(
+                format!(
+                    r##"<sup class="footnote-ref" id="{}"><a href="#fn-{}">{}</a></sup>"##,
+                    id, number, number
+                )
+                .into(),
+            ));
+            continue;
+        }
+
+        if settings.footnote.style == SIDENOTE {
+            result.push(Event::Html(
+                format!(
+                    r#"<sup class="footnote-ref" id="{}">{}</sup><span class="sidenote" id="fn-{}"><sup class="sidenote-number">{}</sup> "#,
+                    id, number, number, number
+                )
+                .into(),
+            ));
+        } else {
+            result.push(Event::Html(
+                format!(
+                    r#"<sup class="footnote-ref" id="{}"><button type="button" popovertarget="fn-{}">{}</button></sup><span class="footnote-popover" id="fn-{}" popover>"#,
+                    id, number, number, number
+                )
+                .into(),
+            ));
+        }
+        // the references in the note are replaced before it is written
+        let note_events = std::mem::take(&mut note.events);
+        let note_events = replace_references(footnotes, note_events, settings);
+        result.extend(flatten(note_events));
+        result.push(Event::Html("</span>".into()));
+    }
+
+    result
+}
+
+/// removes the footnote definitions from `events`, returns their contents by label
+fn take_definitions<'a>(events: &mut Vec<Event<'a>>) -> HashMap<String, Vec<Event<'a>>> {
+    let mut definitions = HashMap::new();
+    let mut current: Option<(String, Vec<Event<'a>>)> = None;
+
+    for ev in std::mem::take(events).into_iter() {
+        match ev {
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                current = Some((label.to_string(), vec![]));
+            }
+            Event::End(Tag::FootnoteDefinition(_)) => {
+                if let Some((label, content)) = current.take() {
+                    definitions.entry(label).or_insert(content);
+                }
+            }
+            ev => match current.as_mut() {
+                Some((_, content)) => content.push(ev),
+                None => events.push(ev),
+            },
+        }
+    }
+
+    definitions
+}
+
+/// the id of the `n`th reference to the footnote `number`
+fn reference_id(number: usize, n: usize) -> String {
+    if n == 1 {
+        format!("fnref-{}", number)
+    } else {
+        format!("fnref-{}-{}", number, n)
+    }
+}
+
+/// the paragraphs of a note written in a line, for a sidenote or a popover in a paragraph
+fn flatten(events: Vec<Event>) -> Vec<Event> {
+    let mut result = vec![];
+    for ev in events.into_iter() {
+        match ev {
+            Event::Start(Tag::Paragraph) => {}
+            Event::End(Tag::Paragraph) => result.push(Event::Html("<br>".into())),
+            ev => result.push(ev),
+        }
+    }
+    // no line break after the last paragraph
+    if matches!(result.last(), Some(Event::Html(h)) if h.as_ref() == "<br>") {
+        result.pop();
+    }
+
+    result
+}
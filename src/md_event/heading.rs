@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{escape::escape_html, html, Event, HeadingLevel, Tag};
+
+use super::toc::TocEntry;
+use super::workarea::Workarea;
+use crate::settings::settings::Settings;
+use crate::slug::slugify;
+
+/// the id of a heading whose text has no letters (e.g. only emoji)
+const EMPTY_SLUG: &str = "section";
+
+/// `<!--- :numbered: true --->` numbers the headings of the page
+pub const NUMBERED_KEY: &str = "numbered";
+/// `# heading {.unnumbered}` is not numbered
+const UNNUMBERED_CLASS: &str = "unnumbered";
+
+/// a heading being processed, it is written as HTML at its end
+pub struct OpenHeading {
+    pub level: HeadingLevel,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    /// index of `Workarea::events` where the content of the heading begins
+    pub begin: usize,
+}
+
+pub fn start(workarea: &mut Workarea, level: HeadingLevel, id: Option<&str>, classes: &[&str]) {
+    workarea.heading = Some(OpenHeading {
+        level: shift(level, workarea.heading_shift),
+        id: id.map(|s| s.to_string()),
+        classes: classes.iter().map(|s| s.to_string()).collect(),
+        begin: workarea.events.len(),
+    });
+}
+
+/// writes the heading with the id given by `{#id}` or generated from its text,
+/// its section number when the headings are numbered,
+/// and an anchor linking to itself when `heading.anchors` is enabled
+pub fn end(workarea: &mut Workarea, settings: &Settings) {
+    let Some(heading) = workarea.heading.take() else {
+        return;
+    };
+
+    let content = &workarea.events[heading.begin..];
+    let text = plain_text(content);
+    // the links are dropped, since the title is linked in the table of contents
+    let mut title = String::new();
+    html::push_html(
+        &mut title,
+        content
+            .iter()
+            .filter(|ev| !matches!(ev, Event::Start(Tag::Link(..)) | Event::End(Tag::Link(..))))
+            .cloned(),
+    );
+
+    let id = match heading.id {
+        Some(id) => {
+            workarea.heading_ids.insert(id.to_owned());
+            Some(id)
+        }
+        None if settings.heading.ids => Some(unique_id(&mut workarea.heading_ids, &text)),
+        None => None,
+    };
+
+    let level = heading.level as usize;
+    let number = if is_numbered(workarea, settings)
+        && !heading.classes.iter().any(|c| c == UNNUMBERED_CLASS)
+    {
+        let top = *workarea.heading_number_top.get_or_insert(level);
+        section_number(&mut workarea.heading_numbers, level, top)
+    } else {
+        None
+    };
+
+    workarea.headings.push(TocEntry {
+        level,
+        id: id.to_owned(),
+        number: number.to_owned(),
+        title,
+        text,
+    });
+
+    if let Some(number) = &number {
+        workarea.events.insert(
+            heading.begin,
+            Event::Html(format!(r#"<span class="section-number">{}</span> "#, number).into()),
+        );
+    }
+
+    let mut tag = format!("<h{}", level);
+    if let Some(id) = &id {
+        tag.push_str(r#" id=""#);
+        escape_html(&mut tag, id).unwrap();
+        tag.push('"');
+    }
+    if !heading.classes.is_empty() {
+        tag.push_str(r#" class=""#);
+        escape_html(&mut tag, &heading.classes.join(" ")).unwrap();
+        tag.push('"');
+    }
+    tag.push('>');
+    workarea
+        .events
+        .insert(heading.begin, Event::Html(tag.into()));
+
+    let mut end_tag = String::new();
+    if let (true, Some(id)) = (settings.heading.anchors, &id) {
+        end_tag.push_str(r##"<a class="anchor" href="#"##);
+        escape_html(&mut end_tag, id).unwrap();
+        end_tag.push_str(r#"" aria-hidden="true">#</a>"#);
+    }
+    end_tag.push_str(&format!("</h{}>\n", level));
+    workarea.push_event(&Event::Html(end_tag.into()));
+}
+
+/// whether the headings are numbered, by `:numbered:` of the page
+/// or `markdown.number_headings`
+fn is_numbered(workarea: &Workarea, settings: &Settings) -> bool {
+    match workarea.meta.get(NUMBERED_KEY) {
+        Some(v) => v == "true",
+        None => settings.markdown.number_headings,
+    }
+}
+
+/// counts up the number of a heading at `level` (e.g. "1.2.3"),
+/// the headings shallower than `top` (the first numbered heading) are not numbered
+fn section_number(counters: &mut [usize; 6], level: usize, top: usize) -> Option<String> {
+    if level < top {
+        return None;
+    }
+
+    counters[level - 1] += 1;
+    counters[level..].iter_mut().for_each(|c| *c = 0);
+
+    Some(
+        counters[top - 1..level]
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<String>>()
+            .join("."),
+    )
+}
+
+/// shifts the level of a heading in an included file, within h1 to h6
+pub fn shift(level: HeadingLevel, shift: i32) -> HeadingLevel {
+    let n = (level as i32 + shift).clamp(1, 6) as usize;
+    HeadingLevel::try_from(n).unwrap_or(level)
+}
+
+/// the text of the events, without the markup
+fn plain_text(events: &[Event]) -> String {
+    events
+        .iter()
+        .filter_map(|ev| match ev {
+            Event::Text(s) | Event::Code(s) => Some(s.as_ref()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// slugifies `text`, and appends a number when the slug is already used in the page
+fn unique_id(ids: &mut HashSet<String>, text: &str) -> String {
+    let mut slug = slugify(text);
+    if slug.is_empty() {
+        slug = EMPTY_SLUG.to_string();
+    }
+
+    let mut id = slug.to_owned();
+    let mut n = 1;
+    while ids.contains(&id) {
+        id = format!("{}-{}", slug, n);
+        n += 1;
+    }
+
+    ids.insert(id.to_owned());
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use pulldown_cmark::HeadingLevel;
+
+    use super::{section_number, shift, unique_id};
+
+    #[test]
+    fn test_unique_id() {
+        let mut ids = HashSet::new();
+        assert_eq!(unique_id(&mut ids, "Getting Started"), "getting-started");
+        assert_eq!(unique_id(&mut ids, "Getting started!"), "getting-started-1");
+        assert_eq!(unique_id(&mut ids, "getting-started"), "getting-started-2");
+        assert_eq!(unique_id(&mut ids, "はじめに"), "はじめに");
+        assert_eq!(unique_id(&mut ids, "はじめに"), "はじめに-1");
+        assert_eq!(unique_id(&mut ids, "🚀"), "section");
+        assert_eq!(unique_id(&mut ids, "🚿"), "section-1");
+    }
+
+    #[test]
+    fn test_shift() {
+        assert_eq!(shift(HeadingLevel::H1, 1), HeadingLevel::H2);
+        assert_eq!(shift(HeadingLevel::H2, -3), HeadingLevel::H1);
+        assert_eq!(shift(HeadingLevel::H5, 3), HeadingLevel::H6);
+    }
+
+    #[test]
+    fn test_section_number() {
+        let mut counters = [0; 6];
+        let mut number = |level| section_number(&mut counters, level, 2);
+        assert_eq!(number(1), None);
+        assert_eq!(number(2).as_deref(), Some("1"));
+        assert_eq!(number(3).as_deref(), Some("1.1"));
+        assert_eq!(number(3).as_deref(), Some("1.2"));
+        assert_eq!(number(4).as_deref(), Some("1.2.1"));
+        assert_eq!(number(2).as_deref(), Some("2"));
+        assert_eq!(number(4).as_deref(), Some("2.0.1"));
+        assert_eq!(number(3).as_deref(), Some("2.1"));
+    }
+}
@@ -0,0 +1,245 @@
+use std::sync::OnceLock;
+
+use pulldown_cmark::escape::escape_html;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{
+    highlighted_html_for_string, styled_line_to_highlighted_html, IncludeBackground,
+};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+use crate::settings::settings::Settings;
+
+/// the lines of a patch out of the hunks, e.g. `git diff`
+const HEADERS: [&str; 5] = ["diff --git ", "index ", "--- ", "+++ ", "@@"];
+
+static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+
+/// the syntaxes and themes are loaded once, at the first code
+fn syntax_set() -> &'static SyntaxSet {
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme(settings: &Settings) -> &'static Theme {
+    &THEME_SET.get_or_init(ThemeSet::load_defaults).themes[&settings.code.highlight.theme]
+}
+
+fn find_syntax(lang: &str) -> &'static SyntaxReference {
+    let ps = syntax_set();
+
+    let language = match lang {
+        "rust" => "Rust",
+        "python" => "Python",
+        "cpp" => "C++",
+        _ => "Plain Text",
+    };
+
+    match ps.find_syntax_by_name(language) {
+        Some(s) => s,
+        None => {
+            eprintln!("{} not found.", lang);
+            ps.find_syntax_plain_text()
+        }
+    }
+}
+
+/// highlights the code of a code block, as `<pre>`
+pub fn block(code: &str, lang: &str, settings: &Settings) -> String {
+    let hh = highlighted_html_for_string(code, syntax_set(), find_syntax(lang), theme(settings));
+
+    hh.unwrap()
+}
+
+/// highlights a patch in the language of its code (e.g. `diff-rust`), marking the lines
+/// starting with `+` and `-` as added and removed.
+/// the file headers (e.g. `--- a/x`) and the hunk headers (`@@ … @@`) are not highlighted.
+pub fn diff(code: &str, lang: &str, settings: &Settings) -> String {
+    let theme = theme(settings);
+    let mut highlighter = HighlightLines::new(find_syntax(lang), theme);
+
+    let mut html = String::from(r#"<pre class="diff""#);
+    push_background(&mut html, theme);
+    html.push_str(">\n");
+
+    // the lines left in the current hunk, as (old, new)
+    let mut hunk = (0, 0);
+    for line in LinesWithEndings::from(code) {
+        let is_header = if hunk == (0, 0) {
+            if line.starts_with("@@") {
+                hunk = hunk_lengths(line);
+            }
+            HEADERS.iter().any(|h| line.starts_with(h))
+        } else {
+            match line.chars().next() {
+                Some('-') => hunk.0 = hunk.0.saturating_sub(1),
+                Some('+') => hunk.1 = hunk.1.saturating_sub(1),
+                Some('\\') => {}
+                _ => hunk = (hunk.0.saturating_sub(1), hunk.1.saturating_sub(1)),
+            }
+            // `\ No newline at end of file`
+            line.starts_with('\\')
+        };
+
+        if is_header {
+            html.push_str(r#"<span class="line header"><span class="gutter"> </span>"#);
+            escape_html(&mut html, line.trim_end_matches(['\r', '\n'])).unwrap();
+            html.push_str("</span>\n");
+            continue;
+        }
+
+        let (class, marker, code) = match line.chars().next() {
+            Some('+') => (" added", "+", &line[1..]),
+            Some('-') => (" removed", "-", &line[1..]),
+            Some(' ') => ("", " ", &line[1..]),
+            _ => ("", " ", line),
+        };
+
+        html.push_str(&format!(
+            r#"<span class="line{}"><span class="gutter">{}</span>"#,
+            class, marker
+        ));
+
+        // the newline is put outside of the line
+        let highlighted = highlighter
+            .highlight_line(code, syntax_set())
+            .and_then(|regions| {
+                let regions = regions
+                    .into_iter()
+                    .map(|(style, s)| (style, s.trim_end_matches(['\r', '\n'])))
+                    .collect::<Vec<_>>();
+                styled_line_to_highlighted_html(&regions, IncludeBackground::No)
+            });
+        match highlighted {
+            Ok(h) => html.push_str(&h),
+            Err(e) => {
+                eprintln!("{}", e);
+                escape_html(&mut html, code.trim_end_matches(['\r', '\n'])).unwrap();
+            }
+        }
+
+        html.push_str("</span>\n");
+    }
+
+    html.push_str("</pre>\n");
+    html
+}
+
+/// the numbers of the old and new lines of a hunk, from `@@ -1,5 +1,6 @@`
+fn hunk_lengths(header: &str) -> (usize, usize) {
+    let length = |prefix: char| {
+        header
+            .split_whitespace()
+            .find_map(|s| s.strip_prefix(prefix))
+            .map_or(0, |range| match range.split_once(',') {
+                Some((_, n)) => n.parse().unwrap_or(0),
+                None => 1,
+            })
+    };
+
+    (length('-'), length('+'))
+}
+
+fn push_background(html: &mut String, theme: &Theme) {
+    if let Some(c) = theme.settings.background {
+        html.push_str(&format!(
+            r#" style="background-color:#{:02x}{:02x}{:02x};""#,
+            c.r, c.g, c.b
+        ));
+    }
+}
+
+/// highlights the code of a code span (e.g. `` `let x = 1;`{:rust} ``), as `<code>`
+pub fn inline(code: &str, lang: &str, settings: &Settings) -> String {
+    let theme = theme(settings);
+    let mut highlighter = HighlightLines::new(find_syntax(lang), theme);
+
+    let mut html = String::from(r#"<code class="language-"#);
+    escape_html(&mut html, lang).unwrap();
+    html.push('"');
+    push_background(&mut html, theme);
+    html.push('>');
+
+    for line in LinesWithEndings::from(code) {
+        let highlighted = highlighter
+            .highlight_line(line, syntax_set())
+            .and_then(|regions| styled_line_to_highlighted_html(&regions, IncludeBackground::No));
+        match highlighted {
+            Ok(h) => html.push_str(&h),
+            Err(e) => {
+                eprintln!("{}", e);
+                escape_html(&mut html, line).unwrap();
+            }
+        }
+    }
+
+    html.push_str("</code>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff;
+    use crate::settings::settings::Settings;
+
+    #[test]
+    fn test_diff() {
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let code = " fn main() {\n-    println!(\"<a>\");\n+    println!(\"テスト🚿\");\n }\n";
+        let expected = concat!(
+            "<pre class=\"diff\" style=\"background-color:#fdf6e3;\">\n",
+            "<span class=\"line\"><span class=\"gutter\"> </span><span style=\"color:#657b83;\">fn main() {</span></span>\n",
+            "<span class=\"line removed\"><span class=\"gutter\">-</span><span style=\"color:#657b83;\">    println!(&quot;&lt;a&gt;&quot;);</span></span>\n",
+            "<span class=\"line added\"><span class=\"gutter\">+</span><span style=\"color:#657b83;\">    println!(&quot;テスト🚿&quot;);</span></span>\n",
+            "<span class=\"line\"><span class=\"gutter\"> </span><span style=\"color:#657b83;\">}</span></span>\n",
+            "</pre>\n",
+        );
+        assert_eq!(expected, diff(code, "", &settings));
+    }
+
+    #[test]
+    fn test_diff_highlighted() {
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let html = diff("+let x = 1;\n", "rust", &settings);
+        assert!(html.contains(
+            r#"<span class="line added"><span class="gutter">+</span><span style="color:#268bd2;">let</span>"#
+        ));
+    }
+
+    #[test]
+    fn test_diff_git() {
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let code = r#"diff --git a/x.rs b/x.rs
+index 11df36f..0d82eb8 100644
+--- a/x.rs
++++ b/x.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    let a = 1;
++    let a = 2;
+ }
+"#;
+        let lines = diff(code, "", &settings)
+            .lines()
+            .map(|l| l.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            lines[1..6],
+            [
+                r#"<span class="line header"><span class="gutter"> </span>diff --git a/x.rs b/x.rs</span>"#,
+                r#"<span class="line header"><span class="gutter"> </span>index 11df36f..0d82eb8 100644</span>"#,
+                r#"<span class="line header"><span class="gutter"> </span>--- a/x.rs</span>"#,
+                r#"<span class="line header"><span class="gutter"> </span>+++ b/x.rs</span>"#,
+                r#"<span class="line header"><span class="gutter"> </span>@@ -1,3 +1,3 @@</span>"#,
+            ]
+        );
+        assert!(lines[7].starts_with(r#"<span class="line removed">"#));
+        assert!(lines[8].starts_with(r#"<span class="line added">"#));
+        assert!(lines[9].starts_with(r#"<span class="line">"#));
+
+        // a removed line `-- comment` in a hunk is not a header
+        let html = diff("@@ -1 +0,0 @@\n--- comment\n", "", &settings);
+        assert!(html.contains(r#"<span class="line removed"><span class="gutter">-</span>"#));
+    }
+}
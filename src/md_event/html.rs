@@ -1,16 +1,81 @@
 use crate::settings::settings::Settings;
 
+use super::include;
+use super::tabs;
+use super::toc;
 use super::workarea::Workarea;
 use pulldown_cmark::{CowStr, Event};
 
+const INCLUDE_KEY: &str = "include";
+const TABS_KEY: &str = "tabs";
+const ENDTABS_KEY: &str = "endtabs";
+const TOC_KEY: &str = "toc";
+const SPECIAL_COMMENT_END: &str = "--->";
+
+/// keys of the special comments which are not metadata
+const DIRECTIVES: [&str; 4] = [INCLUDE_KEY, TABS_KEY, ENDTABS_KEY, TOC_KEY];
+
 pub fn event<'a>(
     workarea: &mut Workarea<'a>,
     settings: &Settings,
     ev: &Event<'a>,
     content: &CowStr,
 ) {
+    let (is_special, tag) = special_comment(workarea, content);
+
+    if let Some((key, value)) = tag {
+        match (key.as_str(), value) {
+            (INCLUDE_KEY, value) => include::event(workarea, settings, &value.unwrap_or_default()),
+            (TABS_KEY, _) => tabs::begin(workarea),
+            (ENDTABS_KEY, _) => tabs::end(workarea),
+            (TOC_KEY, _) => toc::marker(workarea),
+            (_, Some(value)) => {
+                workarea.meta.insert(key, value);
+            }
+            (_, None) => eprintln!(
+                "{}:{}: unknown directive :{}:",
+                workarea.source_name(),
+                workarea.line(),
+                key
+            ),
+        }
+    }
+
+    if is_special {
+        return;
+    }
+
+    workarea.push_event(ev);
+}
+
+/// collects the metadata only, the directives (e.g. `:include:`) are ignored
+pub fn metadata(workarea: &mut Workarea, content: &CowStr) {
+    if let (_, Some((key, Some(value)))) = special_comment(workarea, content) {
+        if !DIRECTIVES.contains(&key.as_str()) {
+            workarea.meta.insert(key, value);
+        }
+    }
+}
+
+/// follows the includes only, for `mtoh tangle`
+pub fn tangle(workarea: &mut Workarea, content: &CowStr) {
+    if let (_, Some((key, Some(value)))) = special_comment(workarea, content) {
+        if key == INCLUDE_KEY {
+            include::tangle(workarea, &value);
+        }
+    }
+}
+
+/// returns whether `content` is (a part of) a special comment,
+/// and the `:key: value` tag in it. the value is `None` for a tag without a value
+/// (e.g. `:tabs:`)
+fn special_comment(
+    workarea: &mut Workarea,
+    content: &CowStr,
+) -> (bool, Option<(String, Option<String>)>) {
     let matches = workarea.re.comment_tag.captures(content);
-    let tag_matched = matches.is_some();
+    let directive = workarea.re.directive.captures(content);
+    let tag_matched = matches.is_some() || directive.is_some();
 
     let special_begin_matched = workarea.re.special_comment_begin.is_match(content);
     let special_end_matched = workarea.re.special_comment_end.is_match(content);
@@ -18,23 +83,26 @@ pub fn event<'a>(
     workarea.is_comment = (special_begin_matched & !special_end_matched)
         | (!special_end_matched & workarea.is_comment);
 
+    let mut tag = None;
     if tag_matched && (special_end_matched || workarea.is_comment) {
-        let captures = matches.as_ref().unwrap();
-
-        let key = captures.name("key");
-        let value = captures.name("value");
+        if let Some(captures) = matches.as_ref() {
+            let key = captures.name("key");
+            let value = captures.name("value");
 
-        if key.is_some() && value.is_some() && !key.unwrap().as_str().starts_with(":") {
-            workarea.meta.insert(
-                key.unwrap().as_str().to_owned(),
-                value.unwrap().as_str().to_owned(),
-            );
+            if let (Some(k), Some(v)) = (key, value) {
+                if !k.as_str().starts_with(':') {
+                    // `<!--- :tabs: --->` has no value but the end of the comment
+                    let v = Some(v.as_str().to_owned()).filter(|v| v != SPECIAL_COMMENT_END);
+                    tag = Some((k.as_str().to_owned(), v));
+                }
+            }
+        } else if let Some(captures) = directive.as_ref() {
+            tag = Some((captures["key"].to_owned(), None));
         }
     }
 
-    if special_begin_matched || special_end_matched || workarea.is_comment {
-        return;
-    }
-
-    workarea.push_event(ev);
+    (
+        special_begin_matched || special_end_matched || workarea.is_comment,
+        tag,
+    )
 }
@@ -0,0 +1,217 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use pulldown_cmark::{escape::escape_html, CodeBlockKind, CowStr, Event, Tag};
+
+use super::heading;
+use super::workarea::Workarea;
+use crate::html_gen;
+use crate::settings::settings::Settings;
+
+const SHIFT_ARG: &str = "shift=";
+
+/// `<!--- :include: snippets/warning.md --->` splices the events of another Markdown file.
+/// `<!--- :include: snippets/install.md shift=1 --->` also shifts its headings by one level.
+pub fn event(workarea: &mut Workarea, settings: &Settings, value: &str) {
+    let Some((sources, shift, text)) = read(workarea, value) else {
+        return;
+    };
+
+    // the variables and the shortcodes are interpolated as in the including file
+    let text = match (workarea.preprocessor, sources.last()) {
+        (Some(p), Some(path)) => p.run(path, &text),
+        _ => text,
+    };
+
+    // the heading ids, numbers and tab groups continue over the including and included files
+    let mut included = Workarea::for_source(sources, workarea.options, &text);
+    included.preprocessor = workarea.preprocessor;
+    included.heading_shift = workarea.heading_shift + shift;
+    included.heading_ids = std::mem::take(&mut workarea.heading_ids);
+    included.heading_numbers = workarea.heading_numbers;
+    included.heading_number_top = workarea.heading_number_top;
+    included.tab_groups = workarea.tab_groups;
+    if let Some(numbered) = workarea.meta.get(heading::NUMBERED_KEY) {
+        included
+            .meta
+            .insert(heading::NUMBERED_KEY.to_string(), numbered.to_owned());
+    }
+
+    let included = html_gen::process_included(&text, included, settings);
+    workarea.heading_ids = included.heading_ids;
+    workarea.heading_numbers = included.heading_numbers;
+    workarea.heading_number_top = included.heading_number_top;
+    workarea.tab_groups = included.tab_groups;
+    workarea.headings.extend(included.headings);
+    for ev in included.events.into_iter() {
+        workarea.push_event(&into_owned(ev));
+    }
+}
+
+/// same as `event`, but only the code blocks of the included file are tangled
+pub fn tangle(workarea: &mut Workarea, value: &str) {
+    let Some((sources, _, text)) = read(workarea, value) else {
+        return;
+    };
+
+    let included = Workarea::for_source(sources, workarea.options, &text);
+    let included = html_gen::tangle_included(&text, included);
+    workarea.tangles.extend(included.tangles);
+}
+
+/// reads the file of `:include:`, returns the sources of the included file,
+/// the shift of its headings and its text. `None` after an error is reported
+fn read(workarea: &Workarea, value: &str) -> Option<(Vec<PathBuf>, i32, String)> {
+    let including = workarea.source_name();
+
+    let (target, shift) = match parse_args(value) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{}: {}", including, e);
+            return None;
+        }
+    };
+
+    let path = workarea.base_dir().join(target);
+
+    if workarea.sources.iter().any(|p| is_same_file(p, &path)) {
+        let chain = workarea
+            .sources
+            .iter()
+            .chain([&path])
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join(" -> ");
+        eprintln!("{}: circular include: {}", including, chain);
+        return None;
+    }
+
+    let text = match fs::read_to_string(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!(
+                "{}: cannot include {}: {}",
+                including,
+                path.to_string_lossy(),
+                e
+            );
+            return None;
+        }
+    };
+
+    let mut sources = workarea.sources.clone();
+    sources.push(path);
+
+    Some((sources, shift, text))
+}
+
+fn parse_args(value: &str) -> Result<(&str, i32), String> {
+    let value = value.trim();
+
+    let (target, shift) = match value.rsplit_once(char::is_whitespace) {
+        Some((t, s)) if s.starts_with(SHIFT_ARG) => match s[SHIFT_ARG.len()..].parse::<i32>() {
+            Ok(n) => (t.trim_end(), n),
+            Err(_) => return Err(format!("invalid include argument: {}", s)),
+        },
+        _ => (value, 0),
+    };
+
+    if target.is_empty() {
+        return Err("include needs a file path".to_string());
+    }
+
+    Ok((target, shift))
+}
+
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn owned_str<'a>(s: CowStr) -> CowStr<'a> {
+    CowStr::from(s.to_string())
+}
+
+fn into_owned_tag<'a>(tag: Tag) -> Tag<'a> {
+    match tag {
+        Tag::Paragraph => Tag::Paragraph,
+        Tag::Heading(level, _, _) => Tag::Heading(level, None, vec![]),
+        Tag::BlockQuote => Tag::BlockQuote,
+        Tag::CodeBlock(CodeBlockKind::Indented) => Tag::CodeBlock(CodeBlockKind::Indented),
+        Tag::CodeBlock(CodeBlockKind::Fenced(s)) => {
+            Tag::CodeBlock(CodeBlockKind::Fenced(owned_str(s)))
+        }
+        Tag::List(n) => Tag::List(n),
+        Tag::Item => Tag::Item,
+        Tag::FootnoteDefinition(s) => Tag::FootnoteDefinition(owned_str(s)),
+        Tag::Table(alignments) => Tag::Table(alignments),
+        Tag::TableHead => Tag::TableHead,
+        Tag::TableRow => Tag::TableRow,
+        Tag::TableCell => Tag::TableCell,
+        Tag::Emphasis => Tag::Emphasis,
+        Tag::Strong => Tag::Strong,
+        Tag::Strikethrough => Tag::Strikethrough,
+        Tag::Link(link_type, url, title) => Tag::Link(link_type, owned_str(url), owned_str(title)),
+        Tag::Image(link_type, url, title) => {
+            Tag::Image(link_type, owned_str(url), owned_str(title))
+        }
+    }
+}
+
+/// the events of an included file borrow its text, which is dropped after the include
+fn into_owned<'a>(ev: Event) -> Event<'a> {
+    match ev {
+        // the id and classes of a heading can only be borrowed, so the tag is written as HTML
+        Event::Start(Tag::Heading(level, id, classes)) if id.is_some() || !classes.is_empty() => {
+            let mut tag = format!("<h{}", level as usize);
+            if let Some(id) = id {
+                tag.push_str(r#" id=""#);
+                escape_html(&mut tag, id).unwrap();
+                tag.push('"');
+            }
+            if !classes.is_empty() {
+                tag.push_str(r#" class=""#);
+                escape_html(&mut tag, &classes.join(" ")).unwrap();
+                tag.push('"');
+            }
+            tag.push('>');
+            Event::Html(tag.into())
+        }
+        Event::End(Tag::Heading(level, id, classes)) if id.is_some() || !classes.is_empty() => {
+            Event::Html(format!("</h{}>\n", level as usize).into())
+        }
+        Event::Start(tag) => Event::Start(into_owned_tag(tag)),
+        Event::End(tag) => Event::End(into_owned_tag(tag)),
+        Event::Text(s) => Event::Text(owned_str(s)),
+        Event::Code(s) => Event::Code(owned_str(s)),
+        Event::Html(s) => Event::Html(owned_str(s)),
+        Event::FootnoteReference(s) => Event::FootnoteReference(owned_str(s)),
+        Event::SoftBreak => Event::SoftBreak,
+        Event::HardBreak => Event::HardBreak,
+        Event::Rule => Event::Rule,
+        Event::TaskListMarker(b) => Event::TaskListMarker(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_args;
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args("snippets/a.md"), Ok(("snippets/a.md", 0)));
+        assert_eq!(
+            parse_args(" snippets/a.md  shift=2 "),
+            Ok(("snippets/a.md", 2))
+        );
+        assert_eq!(
+            parse_args("my snippets/a b.md shift=-1"),
+            Ok(("my snippets/a b.md", -1))
+        );
+        assert!(parse_args("snippets/a.md shift=x").is_err());
+    }
+}
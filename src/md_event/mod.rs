@@ -1,5 +1,17 @@
+pub mod admonition;
+pub mod ansi;
+pub mod code_check;
+pub mod code_exec;
+pub mod code_include;
+pub mod code_info;
 pub mod end;
+pub mod footnote;
+pub mod heading;
+pub mod highlight;
 pub mod html;
+pub mod include;
 pub mod start;
+pub mod tabs;
 pub mod text;
+pub mod toc;
 pub mod workarea;
@@ -0,0 +1,34 @@
+// tab panels of `<!--- :tabs: --->` groups, written by mtoh
+document.querySelectorAll(".tabs").forEach((group) => {
+  const tablist = group.querySelector(":scope > [role=tablist]");
+  const tabs = Array.from(tablist.querySelectorAll("[role=tab]"));
+
+  const select = (selected) => {
+    tabs.forEach((tab) => {
+      const isSelected = tab === selected;
+      tab.setAttribute("aria-selected", isSelected);
+      tab.tabIndex = isSelected ? 0 : -1;
+      document.getElementById(tab.getAttribute("aria-controls")).hidden = !isSelected;
+    });
+  };
+
+  tabs.forEach((tab, i) => {
+    tab.addEventListener("click", () => select(tab));
+    tab.addEventListener("keydown", (e) => {
+      const next = {
+        ArrowLeft: tabs[(i - 1 + tabs.length) % tabs.length],
+        ArrowRight: tabs[(i + 1) % tabs.length],
+        Home: tabs[0],
+        End: tabs[tabs.length - 1],
+      }[e.key];
+      if (next) {
+        e.preventDefault();
+        select(next);
+        next.focus();
+      }
+    });
+  });
+
+  tablist.hidden = false;
+  select(tabs.find((tab) => tab.getAttribute("aria-selected") === "true") || tabs[0]);
+});
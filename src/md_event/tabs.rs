@@ -0,0 +1,100 @@
+use pulldown_cmark::{escape::escape_html, CodeBlockKind, Event, Tag};
+
+use super::code_info::CodeInfo;
+use super::workarea::Workarea;
+
+/// switches the tab panels, written into `output.js_dir`.
+/// without it, the tab list stays hidden and all of the panels are shown.
+pub const SCRIPT: &str = include_str!("tabs.js");
+pub const SCRIPT_NAME: &str = "tabs.js";
+
+/// `<!--- :tabs: --->` begins a group of code blocks shown as tabs
+pub fn begin(workarea: &mut Workarea) {
+    if workarea.tabs_begin.is_some() {
+        eprintln!(
+            "{}:{}: tabs cannot be nested",
+            workarea.source_name(),
+            workarea.line()
+        );
+        return;
+    }
+
+    workarea.tabs_begin = Some(workarea.events.len());
+}
+
+/// `<!--- :endtabs: --->` wraps the code blocks since `:tabs:` into tab panels,
+/// labeled by the `title` attribute or the language of each code block
+pub fn end(workarea: &mut Workarea) {
+    let Some(begin) = workarea.tabs_begin.take() else {
+        eprintln!(
+            "{}:{}: endtabs without tabs",
+            workarea.source_name(),
+            workarea.line()
+        );
+        return;
+    };
+
+    // each panel starts at a code block, and contains the events until the next one
+    // (e.g. the output of an `exec` code block)
+    let mut panels: Vec<(String, Vec<Event>)> = vec![];
+    for ev in workarea.events.split_off(begin) {
+        if let Event::Start(Tag::CodeBlock(kind)) = &ev {
+            panels.push((label(kind), vec![]));
+        }
+
+        match panels.last_mut() {
+            Some((_, events)) => events.push(ev),
+            None => workarea.events.push(ev),
+        }
+    }
+
+    if panels.is_empty() {
+        return;
+    }
+
+    let group = format!("tabs-{}", workarea.tab_groups);
+    workarea.tab_groups += 1;
+
+    let mut html = String::from("<div class=\"tabs\">\n<div role=\"tablist\" hidden>\n");
+    for (i, (label, _)) in panels.iter().enumerate() {
+        let selected = i == 0;
+        html.push_str(&format!(
+            r#"<button type="button" role="tab" id="{0}-{1}" aria-controls="{0}-{1}-panel" aria-selected="{2}" tabindex="{3}">"#,
+            group,
+            i,
+            selected,
+            if selected { 0 } else { -1 }
+        ));
+        escape_html(&mut html, label).unwrap();
+        html.push_str("</button>\n");
+    }
+    html.push_str("</div>\n");
+    workarea.push_event(&Event::Html(html.into()));
+
+    for (i, (_, events)) in panels.into_iter().enumerate() {
+        workarea.push_event(&Event::Html(
+            format!(
+                r#"<div role="tabpanel" id="{0}-{1}-panel" aria-labelledby="{0}-{1}" tabindex="0">"#,
+                group, i
+            )
+            .into(),
+        ));
+        workarea.events.extend(events);
+        workarea.push_event(&Event::Html("</div>\n".into()));
+    }
+
+    workarea.push_event(&Event::Html("</div>\n".into()));
+}
+
+fn label(kind: &CodeBlockKind) -> String {
+    let info = match kind {
+        CodeBlockKind::Fenced(f) => CodeInfo::parse(f),
+        CodeBlockKind::Indented => CodeInfo::default(),
+    };
+
+    match info.get("title") {
+        Some(t) => t.to_string(),
+        None if info.lang.is_empty() => "Code".to_string(),
+        None => info.lang,
+    }
+}
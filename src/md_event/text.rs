@@ -4,6 +4,7 @@ use linked_hash_set::LinkedHashSet;
 use pulldown_cmark::{CowStr, Event};
 use regex::Regex;
 
+use super::highlight;
 use super::workarea::Workarea;
 use crate::settings::settings::Settings;
 
@@ -18,8 +19,144 @@ pub fn event<'a>(
         return;
     }
 
-    let result = replace_emoji_shortcode(&workarea.re.emoji_shortcode, content);
-    workarea.push_event(&Event::Text(result.into()));
+    let content = highlight_code_span(workarea, settings, content);
+    if content.is_empty() {
+        return;
+    }
+
+    let result = replace_emoji_shortcode(&workarea.re.emoji_shortcode, &CowStr::from(content));
+    if !settings.markdown.inline_markup() {
+        workarea.push_event(&Event::Text(result.into()));
+        return;
+    }
+
+    // the syntaxes can be split into the texts (e.g. `[`, `[`, `Ctrl`, `]`, `]`)
+    let text = match workarea.events.last() {
+        Some(Event::Text(last)) => {
+            let text = format!("{}{}", last, result);
+            workarea.events.pop();
+            text
+        }
+        _ => result,
+    };
+    for ev in replace_inline_markup(&workarea.re.inline_markup, settings, &text) {
+        workarea.push_event(&ev);
+    }
+}
+
+/// `==mark==`, `^sup^`, `~sub~`, `++ins++`, `[[kbd]]` and the ruby into the elements,
+/// the others are left as texts
+fn replace_inline_markup<'a>(re: &Regex, settings: &Settings, text: &str) -> Vec<Event<'a>> {
+    let markdown = &settings.markdown;
+    let mut events = vec![];
+    let mut pos = 0;
+
+    for captures in re.captures_iter(text) {
+        let whole = captures.get(0).unwrap();
+        let replaced = if let Some(m) = captures.name("mark").filter(|_| markdown.mark) {
+            element("mark", m.as_str())
+        } else if let Some(m) = captures.name("sup").filter(|_| markdown.superscript) {
+            element("sup", m.as_str())
+        } else if let Some(m) = captures.name("sub").filter(|_| markdown.subscript) {
+            // a part of `~~text~~`
+            if text[..whole.start()].ends_with('~') || text[whole.end()..].starts_with('~') {
+                continue;
+            }
+            element("sub", m.as_str())
+        } else if let Some(m) = captures.name("ins").filter(|_| markdown.inserted) {
+            element("ins", m.as_str())
+        } else if let Some(m) = captures.name("kbd").filter(|_| markdown.keyboard) {
+            element("kbd", m.as_str())
+        } else if let (Some(b), Some(t)) = (captures.name("ruby_base"), captures.name("ruby_text"))
+        {
+            if !markdown.ruby {
+                continue;
+            }
+            ruby(b.as_str(), &t.as_str().split('|').collect::<Vec<&str>>())
+        } else if let Some(t) = captures.name("aozora_text") {
+            if !markdown.ruby {
+                continue;
+            }
+            let b = captures
+                .name("aozora_base")
+                .or_else(|| captures.name("aozora_kanji"))
+                .unwrap();
+            ruby(b.as_str(), &[t.as_str()])
+        } else {
+            continue;
+        };
+
+        if pos < whole.start() {
+            events.push(Event::Text(text[pos..whole.start()].to_string().into()));
+        }
+        events.extend(replaced);
+        pos = whole.end();
+    }
+
+    if pos < text.len() {
+        events.push(Event::Text(text[pos..].to_string().into()));
+    }
+
+    events
+}
+
+fn element<'a>(tag: &str, text: &str) -> Vec<Event<'a>> {
+    vec![
+        Event::Html(format!("<{}>", tag).into()),
+        Event::Text(text.to_string().into()),
+        Event::Html(format!("</{}>", tag).into()),
+    ]
+}
+
+/// `<ruby>漢字<rt>かんじ</rt></ruby>`, or a reading for each character
+/// when there are as many readings as the characters (e.g. `{漢字|かん|じ}`)
+fn ruby<'a>(base: &str, readings: &[&str]) -> Vec<Event<'a>> {
+    let pairs = if readings.len() > 1 && readings.len() == base.chars().count() {
+        base.chars()
+            .map(|c| c.to_string())
+            .zip(readings.iter().map(|r| r.to_string()))
+            .collect::<Vec<(String, String)>>()
+    } else {
+        vec![(base.to_string(), readings.concat())]
+    };
+
+    let mut events = vec![Event::Html("<ruby>".into())];
+    for (b, r) in pairs.into_iter() {
+        events.push(Event::Text(b.into()));
+        events.extend(element("rt", &r));
+    }
+    events.push(Event::Html("</ruby>".into()));
+
+    events
+}
+
+/// `~text~` is parsed as a strikethrough by pulldown-cmark,
+/// returns whether the strikethrough at the current event is a subscript
+pub fn is_subscript(workarea: &Workarea, settings: &Settings) -> bool {
+    let rest = workarea.source.get(workarea.offset..).unwrap_or("");
+    settings.markdown.subscript && rest.starts_with('~') && !rest.starts_with("~~")
+}
+
+/// `` `let x = 1;`{:rust} `` highlights the code span before the text,
+/// returns the text without the language hint
+fn highlight_code_span<'c>(
+    workarea: &mut Workarea,
+    settings: &Settings,
+    content: &'c CowStr,
+) -> &'c str {
+    let Some(Event::Code(code)) = workarea.events.last() else {
+        return content;
+    };
+    let Some(captures) = workarea.re.code_span_hint.captures(content) else {
+        return content;
+    };
+
+    let html = highlight::inline(code, &captures["lang"], settings);
+    let hint_len = captures[0].len();
+
+    workarea.events.pop();
+    workarea.push_event(&Event::Html(html.into()));
+    &content[hint_len..]
 }
 
 fn replace_emoji_shortcode<'a>(re: &Regex, content: &CowStr) -> String {
@@ -57,17 +194,46 @@ fn replace_emoji_shortcode<'a>(re: &Regex, content: &CowStr) -> String {
 
 #[cfg(test)]
 mod tests {
-    use pulldown_cmark::CowStr;
+    use pulldown_cmark::{html, CowStr};
 
     use crate::md_event::workarea::Workarea;
+    use crate::settings::settings::Settings;
 
-    use super::replace_emoji_shortcode;
+    use super::{replace_emoji_shortcode, replace_inline_markup};
 
     fn replace_emoji(content: &str) -> String {
         let workarea = Workarea::new();
         replace_emoji_shortcode(&workarea.re.emoji_shortcode, &CowStr::from(content))
     }
 
+    fn replace_markup(content: &str, settings: &Settings) -> String {
+        let workarea = Workarea::new();
+        let mut result = String::new();
+        html::push_html(
+            &mut result,
+            replace_inline_markup(&workarea.re.inline_markup, settings, content).into_iter(),
+        );
+        result
+    }
+
+    fn replace_ruby(content: &str) -> String {
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.markdown.ruby = true;
+        replace_markup(content, &settings)
+    }
+
+    fn markup_settings() -> Settings {
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.markdown.mark = true;
+        settings.markdown.superscript = true;
+        settings.markdown.subscript = true;
+        settings.markdown.inserted = true;
+        settings.markdown.keyboard = true;
+        settings
+    }
+
     #[test]
     fn test_emoji_only_1() {
         let content = ":rocket:";
@@ -129,4 +295,106 @@ mod tests {
             "::Alice was beginning to get very tired of sitting by her sister on the bank::";
         assert_eq!(replace_emoji(content), content);
     }
+
+    #[test]
+    fn test_inline_markup() {
+        let settings = markup_settings();
+        assert_eq!(
+            replace_markup("==大事== x^2^ H~2~O ++追加++ [[Ctrl]]+[[C]]", &settings),
+            "<mark>大事</mark> x<sup>2</sup> H<sub>2</sub>O <ins>追加</ins> <kbd>Ctrl</kbd>+<kbd>C</kbd>"
+        );
+        assert_eq!(
+            replace_markup("[[<Esc>]] ==a & b==", &settings),
+            "<kbd>&lt;Esc&gt;</kbd> <mark>a &amp; b</mark>"
+        );
+    }
+
+    #[test]
+    fn test_inline_markup_not_matched() {
+        let settings = markup_settings();
+        // spaces are not allowed in the superscripts and subscripts
+        assert_eq!(
+            replace_markup("a ^b c^ d ~e f~", &settings),
+            "a ^b c^ d ~e f~"
+        );
+        assert_eq!(replace_markup("== a==", &settings), "== a==");
+        assert_eq!(replace_markup("~~del~~", &settings), "~~del~~");
+        assert_eq!(replace_markup("1 + 1 ++ 2", &settings), "1 + 1 ++ 2");
+    }
+
+    #[test]
+    fn test_inline_markup_disabled() {
+        let mut settings = markup_settings();
+        settings.markdown.mark = false;
+        settings.markdown.keyboard = false;
+        assert_eq!(
+            replace_markup("==𩸽== [[🚿]] x^2^", &settings),
+            "==𩸽== [[🚿]] x<sup>2</sup>"
+        );
+    }
+
+    #[test]
+    fn test_ruby_only() {
+        let content = "{漢字|かんじ}";
+        assert_eq!(replace_ruby(content), "<ruby>漢字<rt>かんじ</rt></ruby>");
+    }
+
+    #[test]
+    fn test_ruby_per_character() {
+        let content = "{漢字|かん|じ}";
+        assert_eq!(
+            replace_ruby(content),
+            "<ruby>漢<rt>かん</rt>字<rt>じ</rt></ruby>"
+        );
+
+        // the readings are not as many as the characters
+        let content = "{漢字|か|ん|じ}";
+        assert_eq!(replace_ruby(content), "<ruby>漢字<rt>かんじ</rt></ruby>");
+    }
+
+    #[test]
+    fn test_ruby_aozora() {
+        let content = "｜漢字《かんじ》";
+        assert_eq!(replace_ruby(content), "<ruby>漢字<rt>かんじ</rt></ruby>");
+
+        // without `｜`, the kanji before `《` are the base
+        let content = "東京の漢字《かんじ》";
+        assert_eq!(
+            replace_ruby(content),
+            "東京の<ruby>漢字<rt>かんじ</rt></ruby>"
+        );
+        let content = "ひらがな《ひらがな》";
+        assert_eq!(replace_ruby(content), content);
+    }
+
+    #[test]
+    fn test_ruby_with_string() {
+        let content = "これは{漢字|かんじ}と｜振り仮名《ふりがな》です。";
+        assert_eq!(
+            replace_ruby(content),
+            "これは<ruby>漢字<rt>かんじ</rt></ruby>と<ruby>振り仮名<rt>ふりがな</rt></ruby>です。"
+        );
+
+        // with surrogate
+        let content = "𩸽{𩸽|ほっけ}𩸽{𩸽定食|ほっけ|てい|しょく}𩸽｜𩸽《ほっけ》𩸽";
+        assert_eq!(
+            replace_ruby(content),
+            "𩸽<ruby>𩸽<rt>ほっけ</rt></ruby>𩸽<ruby>𩸽<rt>ほっけ</rt>定<rt>てい</rt>食<rt>しょく</rt></ruby>𩸽<ruby>𩸽<rt>ほっけ</rt></ruby>𩸽"
+        );
+    }
+
+    #[test]
+    fn test_ruby_escaped() {
+        let content = "{<b>|&}";
+        assert_eq!(
+            replace_ruby(content),
+            "<ruby>&lt;b&gt;<rt>&amp;</rt></ruby>"
+        );
+    }
+
+    #[test]
+    fn test_ruby_disabled() {
+        let content = "{漢字|かんじ}｜漢字《かんじ》";
+        assert_eq!(replace_markup(content, &markup_settings()), content);
+    }
 }
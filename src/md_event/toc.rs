@@ -0,0 +1,191 @@
+use pulldown_cmark::{escape::escape_html, Event};
+use serde::Serialize;
+
+use super::workarea::Workarea;
+use crate::settings::settings::Settings;
+
+/// written at `<!--- :toc: --->`, and replaced with the table of contents
+/// after all of the headings are seen
+const PLACEHOLDER: &str = "<!--- :toc: --->\n";
+
+/// a heading in the page
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TocEntry {
+    pub level: usize,
+    pub id: Option<String>,
+    /// the section number (e.g. "1.2") when the headings are numbered
+    pub number: Option<String>,
+    /// the content of the heading as HTML, without the links
+    pub title: String,
+    /// the content of the heading without the markup
+    pub text: String,
+}
+
+/// an item of the table of contents, available as `toc_data` in templates
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct TocItem {
+    pub level: usize,
+    pub id: Option<String>,
+    pub number: Option<String>,
+    pub title: String,
+    pub text: String,
+    /// the deeper headings until the next heading of the same or a shallower level
+    pub children: Vec<TocItem>,
+}
+
+/// `<!--- :toc: --->` marks where the table of contents is inserted
+pub fn marker(workarea: &mut Workarea) {
+    workarea.push_event(&Event::Html(PLACEHOLDER.into()));
+}
+
+/// nests the headings within `toc.min_depth` and `toc.max_depth`
+pub fn build(headings: &[TocEntry], settings: &Settings) -> Vec<TocItem> {
+    let mut items: Vec<TocItem> = vec![];
+
+    for heading in headings
+        .iter()
+        .filter(|h| (settings.toc.min_depth..=settings.toc.max_depth).contains(&h.level))
+    {
+        let mut siblings = &mut items;
+        while siblings
+            .last()
+            .is_some_and(|last| last.level < heading.level)
+        {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+
+        siblings.push(TocItem {
+            level: heading.level,
+            id: heading.id.to_owned(),
+            number: heading.number.to_owned(),
+            title: heading.title.to_owned(),
+            text: heading.text.to_owned(),
+            children: vec![],
+        });
+    }
+
+    items
+}
+
+/// renders the table of contents as `<nav class="toc">`, empty when there are no headings
+pub fn render(items: &[TocItem]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from("<nav class=\"toc\">\n");
+    render_list(items, &mut html);
+    html.push_str("</nav>\n");
+    html
+}
+
+fn render_list(items: &[TocItem], html: &mut String) {
+    html.push_str("<ul>\n");
+    for item in items.iter() {
+        let mut title = String::new();
+        if let Some(number) = &item.number {
+            title.push_str(&format!(
+                r#"<span class="section-number">{}</span> "#,
+                number
+            ));
+        }
+        title.push_str(&item.title);
+
+        html.push_str("<li>");
+        match &item.id {
+            Some(id) => {
+                html.push_str("<a href=\"#");
+                escape_html(&mut *html, id).unwrap();
+                html.push_str("\">");
+                html.push_str(&title);
+                html.push_str("</a>");
+            }
+            None => html.push_str(&title),
+        }
+        if !item.children.is_empty() {
+            html.push('\n');
+            render_list(&item.children, html);
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n");
+}
+
+/// replaces the placeholders of `<!--- :toc: --->` with `toc`
+pub fn insert(events: &mut [Event], toc: &str) {
+    for ev in events.iter_mut() {
+        if matches!(ev, Event::Html(s) if s.as_ref() == PLACEHOLDER) {
+            *ev = Event::Html(toc.to_string().into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build, render, TocEntry};
+    use crate::settings::settings::Settings;
+
+    fn entry(level: usize, id: &str, title: &str) -> TocEntry {
+        TocEntry {
+            level,
+            id: Some(id.to_string()),
+            number: None,
+            title: title.to_string(),
+            text: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.toc.min_depth = 1;
+        settings.toc.max_depth = 3;
+
+        let headings = vec![
+            entry(1, "title", "Title"),
+            entry(3, "deep", "Deep"),
+            entry(2, "はじめに", "はじめに"),
+            entry(3, "🚿", "🚿"),
+            entry(4, "ignored", "Ignored"),
+            entry(2, "𩸽", "𩸽"),
+        ];
+        let items = build(&headings, &settings);
+
+        assert_eq!(items.len(), 1);
+        let titles = |items: &[super::TocItem]| {
+            items
+                .iter()
+                .map(|i| i.title.to_owned())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(titles(&items[0].children), ["Deep", "はじめに", "𩸽"]);
+        assert_eq!(titles(&items[0].children[1].children), ["🚿"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let settings = Settings::new(None, None, None, None, None, None, None, None, None, None);
+        let mut headings = vec![
+            entry(1, "title", "Title"),
+            entry(2, "install", "<em>Install</em>"),
+            entry(3, "linux", "Linux"),
+            entry(2, "使い方", "使い方"),
+        ];
+        headings[3].id = None;
+
+        let expected = r##"<nav class="toc">
+<ul>
+<li><a href="#install"><em>Install</em></a>
+<ul>
+<li><a href="#linux">Linux</a></li>
+</ul>
+</li>
+<li>使い方</li>
+</ul>
+</nav>
+"##;
+        assert_eq!(expected, render(&build(&headings, &settings)));
+        assert_eq!("", render(&[]));
+    }
+}
@@ -1,13 +1,33 @@
-use pulldown_cmark::Event;
+use pulldown_cmark::{Event, Options};
 use regex::Regex;
-use std::collections::HashMap;
+
+use super::heading::OpenHeading;
+use super::toc::TocEntry;
+use crate::preprocess::Preprocessor;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 const COMMENT_BEGIN: &str = r"<!--";
 const COMMENT_END: &str = r"-->";
 const SPECIAL_COMMENT_BEGIN: &str = r"<!---";
 const SPECIAL_COMMENT_END: &str = r"--->";
 const COMMENT_TAG: &str = r#":(?P<key>.+): *["']?(?P<value>.+?)["']? *"#;
+const DIRECTIVE: &str = r":(?P<key>[\w-]+):";
 const EMOJI_SHORTCODE: &str = ":[a-zA-Z0-9]+?:";
+const CODE_SPAN_HINT: &str = r"^\{:(?P<lang>[\w+#.-]+)\}";
+const INLINE_MARKUP: &str = concat!(
+    r"==(?P<mark>[^=\s](?:[^=]*[^=\s])?)==",
+    r"|\^(?P<sup>[^\^\s]+)\^",
+    r"|~(?P<sub>[^~\s]+)~",
+    r"|\+\+(?P<ins>[^+\s](?:[^+]*[^+\s])?)\+\+",
+    r"|\[\[(?P<kbd>[^\[\]]+)\]\]",
+    r"|\{(?P<ruby_base>[^{}|]+)\|(?P<ruby_text>[^{}]+)\}",
+    r"|(?:｜(?P<aozora_base>[^｜《》]+)|(?P<aozora_kanji>[\p{Han}々〆ヵヶ]+))《(?P<aozora_text>[^《》]+)》",
+);
+const ADMONITION_CALLOUT: &str = r"^\[!(?P<kind>[\w-]+)\](?P<fold>[+-])?(?:\s+(?P<title>.*))?$";
+const ADMONITION_CONTAINER: &str = r"^:::\s*(?P<kind>[\w-]+)(?P<fold>[+-])?(?:\s+(?P<title>.*))?$";
 
 pub struct ReCollection {
     pub comment_begin: Regex,
@@ -15,7 +35,12 @@ pub struct ReCollection {
     pub special_comment_begin: Regex,
     pub special_comment_end: Regex,
     pub comment_tag: Regex,
+    pub directive: Regex,
     pub emoji_shortcode: Regex,
+    pub code_span_hint: Regex,
+    pub inline_markup: Regex,
+    pub admonition_callout: Regex,
+    pub admonition_container: Regex,
 }
 
 impl ReCollection {
@@ -40,17 +65,48 @@ impl ReCollection {
             panic!("regex compile failed: {}", COMMENT_TAG);
         };
 
+        let Ok(re_directive) = Regex::new(
+            format!(
+                r"(?:^\s*|{} *){} *(?:[\r\n]*$|{})",
+                SPECIAL_COMMENT_BEGIN, DIRECTIVE, SPECIAL_COMMENT_END
+            )
+            .as_str(),
+        ) else {
+            panic!("regex compile failed: {}", DIRECTIVE);
+        };
+
         let Ok(re_emoji_shortcode) = Regex::new(EMOJI_SHORTCODE) else {
             panic!("regex compile failed: {}", EMOJI_SHORTCODE);
         };
 
+        let Ok(re_code_span_hint) = Regex::new(CODE_SPAN_HINT) else {
+            panic!("regex compile failed: {}", CODE_SPAN_HINT);
+        };
+
+        let Ok(re_inline_markup) = Regex::new(INLINE_MARKUP) else {
+            panic!("regex compile failed: {}", INLINE_MARKUP);
+        };
+
+        let Ok(re_admonition_callout) = Regex::new(ADMONITION_CALLOUT) else {
+            panic!("regex compile failed: {}", ADMONITION_CALLOUT);
+        };
+
+        let Ok(re_admonition_container) = Regex::new(ADMONITION_CONTAINER) else {
+            panic!("regex compile failed: {}", ADMONITION_CONTAINER);
+        };
+
         Self {
             comment_begin: re_comment_begin,
             comment_end: re_comment_end,
             special_comment_begin: re_special_comment_begin,
             special_comment_end: re_special_comment_end,
             comment_tag: re_comment_tag,
+            directive: re_directive,
             emoji_shortcode: re_emoji_shortcode,
+            code_span_hint: re_code_span_hint,
+            inline_markup: re_inline_markup,
+            admonition_callout: re_admonition_callout,
+            admonition_container: re_admonition_container,
         }
     }
 }
@@ -63,6 +119,48 @@ pub struct Workarea<'a> {
 
     pub meta: HashMap<String, String>,
 
+    /// the Markdown file being processed and the files including it (the last one is current)
+    pub sources: Vec<PathBuf>,
+    /// the parser options of the Markdown file, used for the included files
+    pub options: Options,
+    /// interpolates the variables and the shortcodes of the included files
+    pub preprocessor: Option<&'a Preprocessor<'a>>,
+
+    /// the Markdown text, empty when unknown
+    pub source: &'a str,
+    /// byte offset of the current event in the Markdown text
+    pub offset: usize,
+    /// byte offsets where each line begins, empty when the text is unknown
+    pub line_starts: Vec<usize>,
+
+    /// code blocks with the `file` attribute, as (file, code) in document order
+    pub tangles: Vec<(String, String)>,
+
+    /// the heading being processed
+    pub heading: Option<OpenHeading>,
+    /// ids of the headings so far, shared with the included files
+    pub heading_ids: HashSet<String>,
+    /// levels added to the headings of an included file (`shift=N`)
+    pub heading_shift: i32,
+    /// headings in document order, for the table of contents
+    pub headings: Vec<TocEntry>,
+    /// the last section number of each heading level
+    pub heading_numbers: [usize; 6],
+    /// the level of the first numbered heading, the top of the section numbers
+    pub heading_number_top: Option<usize>,
+
+    /// index of `events` where the open `:tabs:` group begins
+    pub tabs_begin: Option<usize>,
+    /// number of the tab groups so far, for their ids
+    pub tab_groups: usize,
+
+    /// index of `events` where the current paragraph begins
+    pub paragraph_begin: Option<usize>,
+    /// indexes of `events` where the open block quotes begin
+    pub quote_begins: Vec<usize>,
+    /// the open `:::` containers, whether collapsible (`<details>`) and the line of each
+    pub containers: Vec<(bool, usize)>,
+
     pub is_comment: bool,
     pub is_code: bool,
     pub is_html: bool,
@@ -78,12 +176,79 @@ impl<'a> Workarea<'a> {
 
             meta: HashMap::new(),
 
+            sources: vec![],
+            options: Options::empty(),
+            preprocessor: None,
+
+            source: "",
+            offset: 0,
+            line_starts: vec![],
+
+            tangles: vec![],
+
+            heading: None,
+            heading_ids: HashSet::new(),
+            heading_shift: 0,
+            headings: vec![],
+            heading_numbers: [0; 6],
+            heading_number_top: None,
+
+            tabs_begin: None,
+            tab_groups: 0,
+
+            paragraph_begin: None,
+            quote_begins: vec![],
+            containers: vec![],
+
             is_comment: false,
             is_code: false,
             is_html: false,
         }
     }
 
+    pub fn for_source(sources: Vec<PathBuf>, options: Options, text: &'a str) -> Self {
+        let line_starts = [0]
+            .into_iter()
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        Self {
+            sources,
+            options,
+            source: text,
+            line_starts,
+            ..Self::new()
+        }
+    }
+
+    /// 1-based line of the current event, 0 when the text is unknown
+    pub fn line(&self) -> usize {
+        if self.line_starts.is_empty() {
+            return 0;
+        }
+
+        match self.line_starts.binary_search(&self.offset) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        }
+    }
+
+    /// the Markdown file being processed, for messages
+    pub fn source_name(&self) -> String {
+        match self.sources.last() {
+            Some(p) => p.to_string_lossy().to_string(),
+            None => "(input)".to_string(),
+        }
+    }
+
+    /// the directory which relative paths in the Markdown file are resolved from
+    pub fn base_dir(&self) -> PathBuf {
+        match self.sources.last().and_then(|p| p.parent()) {
+            Some(p) => p.to_path_buf(),
+            None => Path::new("").to_path_buf(),
+        }
+    }
+
     pub fn break_frags(&mut self) {
         self.is_comment = false;
         self.is_code = false;
@@ -0,0 +1,497 @@
+use std::path::Path;
+
+use handlebars::Handlebars;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+const SHORTCODE_BEGIN: &str = "{{<";
+const SHORTCODE_END: &str = ">}}";
+const VARIABLE_BEGIN: &str = "{{";
+const VARIABLE_END: &str = "}}";
+const ESCAPED_VARIABLE_BEGIN: &str = r"\{{";
+const SHORTCODE_ARG: &str =
+    r#"(?:(?P<key>[\w-]+)=)?(?:"(?P<quoted>[^"]*)"|'(?P<single>[^']*)'|(?P<bare>[^\s"']+))"#;
+
+/// partials named `shortcodes/<name>` are the shortcodes
+const SHORTCODE_PARTIAL_DIR: &str = "shortcodes";
+
+/// replaces `{{ meta.version }}`-style variables and `{{< youtube id="…" >}}`-style shortcodes
+/// in a Markdown source. code blocks and code spans are left as they are.
+pub struct Preprocessor<'a> {
+    data: &'a Value,
+    registry: &'a Handlebars<'a>,
+    re_shortcode_arg: Regex,
+}
+
+impl<'a> Preprocessor<'a> {
+    /// `data` is the lookup root of the variables (e.g. `{"meta": …, "site": …, "vars": …}`)
+    pub fn new(data: &'a Value, registry: &'a Handlebars<'a>) -> Self {
+        let Ok(re_shortcode_arg) = Regex::new(SHORTCODE_ARG) else {
+            panic!("regex compile failed: {}", SHORTCODE_ARG);
+        };
+
+        Self {
+            data,
+            registry,
+            re_shortcode_arg,
+        }
+    }
+
+    /// `path` is the file of `text` (e.g. an included snippet), for the messages
+    pub fn run(&self, path: &Path, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+
+        // (marker char, marker length, blockquote depth) of the open code fence
+        let mut fence: Option<(char, usize, usize)> = None;
+        // the column of the content of the current list item
+        let mut list_indent: Option<usize> = None;
+        let mut is_prev_blank = true;
+        let mut is_indented_code = false;
+
+        for (i, line) in text.split_inclusive('\n').enumerate() {
+            if let Some((c, n, depth)) = fence {
+                let (quoted, _) = strip_quote(line, depth);
+                let inner = strip_list_indent(quoted, list_indent);
+                let trimmed = inner.trim_start_matches(' ');
+                if inner.len() - trimmed.len() < 4 && is_closing_fence(trimmed, c, n) {
+                    fence = None;
+                }
+                result.push_str(line);
+                continue;
+            }
+
+            // fences and indented code are also in blockquotes and list items
+            let (quoted, depth) = strip_quote(line, usize::MAX);
+            let trimmed = quoted.trim_start_matches(' ');
+            let item = match list_marker(trimmed) {
+                Some(w) if quoted.len() - trimmed.len() < 4 => Some(w),
+                _ => None,
+            };
+            let inner = match item {
+                Some(w) => {
+                    list_indent = Some(quoted.len() - trimmed.len() + w);
+                    &trimmed[w..]
+                }
+                None => {
+                    if quoted.len() == trimmed.len() && !quoted.trim().is_empty() {
+                        list_indent = None;
+                    }
+                    strip_list_indent(quoted, list_indent)
+                }
+            };
+
+            let trimmed = inner.trim_start_matches(' ');
+            let indent = inner.len() - trimmed.len();
+            let is_blank = inner.trim().is_empty();
+
+            if indent < 4 {
+                if let Some((c, n)) = opening_fence(trimmed) {
+                    fence = Some((c, n, depth));
+                    result.push_str(line);
+                    continue;
+                }
+            }
+
+            // an indented code block starts after a blank line and continues over blank lines
+            let is_indented = !is_blank && (indent >= 4 || inner.starts_with('\t'));
+            is_indented_code = if is_indented {
+                is_prev_blank || is_indented_code
+            } else {
+                is_indented_code && is_blank
+            };
+            is_prev_blank = is_blank;
+
+            if is_indented_code {
+                result.push_str(line);
+                continue;
+            }
+
+            result.push_str(&self.run_line(line, path, i + 1));
+        }
+
+        result
+    }
+
+    fn run_line(&self, line: &str, path: &Path, line_no: usize) -> String {
+        let mut result = String::with_capacity(line.len());
+        let mut pos = 0;
+
+        while pos < line.len() {
+            let rest = &line[pos..];
+
+            if rest.starts_with('`') {
+                // code span
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                let end = match find_backtick_run(&rest[ticks..], ticks) {
+                    Some(e) => ticks + e + ticks,
+                    None => ticks,
+                };
+                result.push_str(&rest[..end]);
+                pos += end;
+            } else if rest.starts_with(ESCAPED_VARIABLE_BEGIN) {
+                result.push_str(VARIABLE_BEGIN);
+                pos += ESCAPED_VARIABLE_BEGIN.len();
+            } else if rest.starts_with(SHORTCODE_BEGIN) && rest.contains(SHORTCODE_END) {
+                let end = rest.find(SHORTCODE_END).unwrap();
+                let inner = &rest[SHORTCODE_BEGIN.len()..end];
+                let whole = &rest[..end + SHORTCODE_END.len()];
+                match self.render_shortcode(inner) {
+                    Ok(s) => result.push_str(&s),
+                    Err(e) => {
+                        eprintln!("{}:{}: {}", path.to_string_lossy(), line_no, e);
+                        result.push_str(whole);
+                    }
+                }
+                pos += whole.len();
+            } else if rest.starts_with(VARIABLE_BEGIN) && rest.contains(VARIABLE_END) {
+                let end = rest.find(VARIABLE_END).unwrap();
+                let name = rest[VARIABLE_BEGIN.len()..end].trim();
+                let whole = &rest[..end + VARIABLE_END.len()];
+                match self.lookup(name) {
+                    Some(s) => result.push_str(&s),
+                    None => {
+                        eprintln!(
+                            "{}:{}: unknown variable \"{}\"",
+                            path.to_string_lossy(),
+                            line_no,
+                            name
+                        );
+                        result.push_str(whole);
+                    }
+                }
+                pos += whole.len();
+            } else {
+                let c = rest.chars().next().unwrap();
+                result.push(c);
+                pos += c.len_utf8();
+            }
+        }
+
+        result
+    }
+
+    /// looks up a dotted path (e.g. `site.analytics.id`) in the data
+    fn lookup(&self, name: &str) -> Option<String> {
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut value = self.data;
+        for key in name.split('.') {
+            value = match value {
+                Value::Object(m) => m.get(key)?,
+                Value::Array(a) => a.get(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+
+        match value {
+            Value::String(s) => Some(s.to_owned()),
+            Value::Null | Value::Object(_) | Value::Array(_) => None,
+            v => Some(v.to_string()),
+        }
+    }
+
+    fn render_shortcode(&self, inner: &str) -> Result<String, String> {
+        let inner = inner.trim();
+        let name = inner.split_whitespace().next().unwrap_or("");
+        let template = format!("{}/{}", SHORTCODE_PARTIAL_DIR, name);
+        if name.is_empty() || !self.registry.has_template(&template) {
+            return Err(format!("unknown shortcode \"{}\"", name));
+        }
+
+        // named arguments are passed by their names, and all of arguments as `params`
+        let mut args = match self.data {
+            Value::Object(m) => m.clone(),
+            _ => Map::new(),
+        };
+        let mut params: Vec<Value> = vec![];
+        for c in self.re_shortcode_arg.captures_iter(&inner[name.len()..]) {
+            let value = c
+                .name("quoted")
+                .or_else(|| c.name("single"))
+                .or_else(|| c.name("bare"))
+                .map_or("", |m| m.as_str());
+
+            if let Some(key) = c.name("key") {
+                args.insert(key.as_str().to_string(), Value::from(value));
+            }
+            params.push(Value::from(value));
+        }
+        args.insert("params".to_string(), Value::from(params));
+
+        // the output is joined into one line, so that the lines after it keep their numbers
+        // in the messages of the later passes
+        match self.registry.render(&template, &args) {
+            Ok(s) => Ok(s.lines().collect::<Vec<&str>>().join(" ")),
+            Err(e) => Err(format!("shortcode \"{}\": {}", name, e)),
+        }
+    }
+}
+
+/// removes up to `depth` blockquote markers (`> `) from the start of `line`,
+/// returns the rest and the number of the removed markers
+fn strip_quote(line: &str, depth: usize) -> (&str, usize) {
+    let mut rest = line;
+    let mut n = 0;
+    while n < depth {
+        let trimmed = rest.trim_start_matches(' ');
+        if rest.len() - trimmed.len() >= 4 {
+            break;
+        }
+        let Some(r) = trimmed.strip_prefix('>') else {
+            break;
+        };
+        rest = r.strip_prefix(' ').unwrap_or(r);
+        n += 1;
+    }
+
+    (rest, n)
+}
+
+/// removes the indent of the list item content from `line`
+fn strip_list_indent(line: &str, list_indent: Option<usize>) -> &str {
+    match list_indent {
+        Some(n) if line.len() - line.trim_start_matches(' ').len() >= n => &line[n..],
+        _ => line,
+    }
+}
+
+/// returns the width of a list marker (e.g. "- ", "10. ") with the spaces after it
+fn list_marker(line: &str) -> Option<usize> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = match digits {
+        0 if line.starts_with(['-', '*', '+']) => 1,
+        1..=9 if line[digits..].starts_with(['.', ')']) => digits + 1,
+        _ => return None,
+    };
+
+    let rest = &line[marker..];
+    if rest.trim().is_empty() {
+        // an empty item
+        return Some(marker + 1);
+    }
+    match rest.len() - rest.trim_start_matches(' ').len() {
+        0 => None,
+        // the content is an indented code block, which begins after one space
+        5.. => Some(marker + 1),
+        spaces => Some(marker + spaces),
+    }
+}
+
+/// returns the marker of a code fence (e.g. "```rust" -> ('`', 3))
+fn opening_fence(line: &str) -> Option<(char, usize)> {
+    let c = line.chars().next()?;
+    if c != '`' && c != '~' {
+        return None;
+    }
+
+    let n = line.len() - line.trim_start_matches(c).len();
+    if n < 3 {
+        return None;
+    }
+
+    // the info string of a backtick fence cannot contain backticks
+    if c == '`' && line[n..].contains('`') {
+        return None;
+    }
+
+    Some((c, n))
+}
+
+fn is_closing_fence(line: &str, c: char, n: usize) -> bool {
+    let rest = line.trim_start_matches(c);
+    line.len() - rest.len() >= n && rest.trim().is_empty()
+}
+
+/// finds a run of exactly `ticks` backticks
+fn find_backtick_run(s: &str, ticks: usize) -> Option<usize> {
+    let mut pos = 0;
+    while let Some(i) = s[pos..].find('`') {
+        let start = pos + i;
+        let run = s[start..].len() - s[start..].trim_start_matches('`').len();
+        if run == ticks {
+            return Some(start);
+        }
+        pos = start + run;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use super::Preprocessor;
+
+    fn preprocess(text: &str) -> String {
+        let mut registry = Handlebars::new();
+        registry
+            .register_partial(
+                "shortcodes/youtube",
+                r#"<iframe src="https://www.youtube.com/embed/{{id}}" title="{{site.name}}"></iframe>"#,
+            )
+            .unwrap();
+        registry
+            .register_partial("shortcodes/kbd", "<kbd>{{params.[0]}}</kbd>")
+            .unwrap();
+        registry
+            .register_partial(
+                "shortcodes/note",
+                "<div class=\"note\">\n{{params.[0]}}\n</div>\n",
+            )
+            .unwrap();
+
+        let data = json!({
+            "meta": {"version": "1.2.3", "テスト": "データ"},
+            "site": {"name": "mtoh", "analytics": {"id": "UA-1"}},
+            "vars": {"year": 2022},
+        });
+        Preprocessor::new(&data, &registry).run(Path::new("test.md"), text)
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(
+            preprocess("version {{ meta.version }} of {{site.name}} ({{ vars.year }})\n"),
+            "version 1.2.3 of mtoh (2022)\n"
+        );
+        assert_eq!(
+            preprocess("{{ site.analytics.id }} {{ meta.テスト }}"),
+            "UA-1 データ"
+        );
+    }
+
+    #[test]
+    fn test_variables_unknown() {
+        assert_eq!(
+            preprocess("{{ meta.unknown }} {{ site }} {{ }}"),
+            "{{ meta.unknown }} {{ site }} {{ }}"
+        );
+    }
+
+    #[test]
+    fn test_variables_escaped() {
+        assert_eq!(preprocess(r"\{{ site.name }}"), "{{ site.name }}");
+    }
+
+    #[test]
+    fn test_shortcodes() {
+        assert_eq!(
+            preprocess("{{< youtube id=\"abc\" >}}\n"),
+            "<iframe src=\"https://www.youtube.com/embed/abc\" title=\"mtoh\"></iframe>\n"
+        );
+        assert_eq!(
+            preprocess("press {{< kbd Ctrl >}}"),
+            "press <kbd>Ctrl</kbd>"
+        );
+
+        // a multi-line output is kept on the line of the shortcode
+        assert_eq!(
+            preprocess("{{< note hi >}}\nnext\n"),
+            "<div class=\"note\"> hi </div>\nnext\n"
+        );
+    }
+
+    #[test]
+    fn test_shortcodes_unknown() {
+        assert_eq!(
+            preprocess("{{< vimeo id=\"abc\" >}}"),
+            "{{< vimeo id=\"abc\" >}}"
+        );
+    }
+
+    #[test]
+    fn test_code_is_untouched() {
+        let md = r#"`{{ site.name }}` and ``{{ site.name }} ` `` and {{ site.name }}
+
+```hbs
+{{ site.name }}
+{{< youtube id="abc" >}}
+```
+
+~~~~
+{{ site.name }}
+~~~
+~~~~
+
+    {{ site.name }}
+
+    {{ site.name }}
+{{ site.name }}
+"#;
+        let expected = r#"`{{ site.name }}` and ``{{ site.name }} ` `` and mtoh
+
+```hbs
+{{ site.name }}
+{{< youtube id="abc" >}}
+```
+
+~~~~
+{{ site.name }}
+~~~
+~~~~
+
+    {{ site.name }}
+
+    {{ site.name }}
+mtoh
+"#;
+        assert_eq!(preprocess(md), expected);
+    }
+
+    #[test]
+    fn test_code_in_containers_is_untouched() {
+        let md = r#"> ```
+> {{ site.name }}
+> ```
+> {{ site.name }}
+
+- item
+
+  ```hbs
+  {{ site.name }}
+  ```
+1. ~~~
+   {{ site.name }}
+   ~~~
+   {{ site.name }}
+   - nested
+
+         {{ site.name }}
+
+     ```
+     {{ site.name }}
+     ```
+{{ site.name }}
+"#;
+        let expected = r#"> ```
+> {{ site.name }}
+> ```
+> mtoh
+
+- item
+
+  ```hbs
+  {{ site.name }}
+  ```
+1. ~~~
+   {{ site.name }}
+   ~~~
+   mtoh
+   - nested
+
+         {{ site.name }}
+
+     ```
+     {{ site.name }}
+     ```
+mtoh
+"#;
+        assert_eq!(preprocess(md), expected);
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// the types of admonitions known without the settings, as (type, label, icon)
+const DEFAULT_TYPES: [(&str, &str, &str); 5] = [
+    ("note", "Note", "ℹ️"),
+    ("tip", "Tip", "💡"),
+    ("important", "Important", "❗"),
+    ("warning", "Warning", "⚠️"),
+    ("caution", "Caution", "🛑"),
+];
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Admonition {
+    /// types in addition to (or replacing) note, tip, important, warning and caution,
+    /// e.g. `[admonition.types.danger]`
+    #[serde(default)]
+    pub types: HashMap<String, AdmonitionType>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AdmonitionType {
+    /// the title when the admonition has no title
+    pub label: String,
+    /// written before the title as HTML (e.g. an emoji or `<svg>`)
+    #[serde(default)]
+    pub icon: String,
+}
+
+impl Admonition {
+    pub fn new(types: Option<HashMap<String, AdmonitionType>>) -> Self {
+        Self {
+            types: types.unwrap_or_default(),
+        }
+    }
+
+    /// the type named `name` (case-insensitive) in the settings or the defaults
+    pub fn get(&self, name: &str) -> Option<AdmonitionType> {
+        let name = name.to_lowercase();
+        if let Some((_, t)) = self.types.iter().find(|(k, _)| k.to_lowercase() == name) {
+            return Some(t.to_owned());
+        }
+
+        DEFAULT_TYPES
+            .iter()
+            .find(|(n, _, _)| *n == name)
+            .map(|(_, label, icon)| AdmonitionType {
+                label: label.to_string(),
+                icon: icon.to_string(),
+            })
+    }
+}
+
+impl Default for Admonition {
+    fn default() -> Self {
+        Admonition::new(None)
+    }
+}
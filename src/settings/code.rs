@@ -1,26 +1,41 @@
 use serde::{Deserialize, Serialize};
 
+use crate::settings::code_settings::check::CodeCheck;
+use crate::settings::code_settings::exec::CodeExec;
 use crate::settings::code_settings::highlight::CodeHighlight;
 
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Code {
     #[serde(default)]
     pub highlight: CodeHighlight,
+    #[serde(default)]
+    pub check: CodeCheck,
+    #[serde(default)]
+    pub exec: CodeExec,
 }
 
 impl Code {
-    pub fn new(highlight: Option<CodeHighlight>) -> Self {
+    pub fn new(
+        highlight: Option<CodeHighlight>,
+        check: Option<CodeCheck>,
+        exec: Option<CodeExec>,
+    ) -> Self {
         Self {
             highlight: match highlight {
                 Some(o) => o,
-                None => CodeHighlight::new(None),
+                None => CodeHighlight::new(None, None),
+            },
+            check: match check {
+                Some(o) => o,
+                None => CodeCheck::new(None),
             },
+            exec: exec.unwrap_or_default(),
         }
     }
 }
 
 impl Default for Code {
     fn default() -> Self {
-        Code::new(None)
+        Code::new(None, None, None)
     }
 }
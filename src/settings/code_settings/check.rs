@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CodeCheck {
+    /// validates `json`, `toml` and `yaml` code blocks
+    #[serde(default = "default_code_check_enabled")]
+    pub enabled: bool,
+}
+
+fn default_code_check_enabled() -> bool {
+    CodeCheck::default().enabled
+}
+
+impl CodeCheck {
+    pub fn new(enabled: Option<bool>) -> Self {
+        Self {
+            enabled: enabled.unwrap_or(false),
+        }
+    }
+}
+
+impl Default for CodeCheck {
+    fn default() -> Self {
+        CodeCheck::new(None)
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CodeExec {
+    /// runs code blocks with the `exec` attribute
+    #[serde(default = "default_code_exec_enabled")]
+    pub enabled: bool,
+    /// command line for each language, the code is given to its stdin
+    /// (e.g. `sh = ["sh"]`, `python = ["python3", "-"]`)
+    #[serde(default)]
+    pub interpreters: HashMap<String, Vec<String>>,
+    /// seconds until the command is killed
+    #[serde(default = "default_code_exec_timeout")]
+    pub timeout: u64,
+    #[serde(default = "default_code_exec_cache_dir")]
+    pub cache_dir: String,
+}
+
+fn default_code_exec_enabled() -> bool {
+    CodeExec::default().enabled
+}
+
+fn default_code_exec_timeout() -> u64 {
+    CodeExec::default().timeout
+}
+
+fn default_code_exec_cache_dir() -> String {
+    CodeExec::default().cache_dir
+}
+
+impl CodeExec {
+    pub fn new(
+        enabled: Option<bool>,
+        interpreters: Option<HashMap<String, Vec<String>>>,
+        timeout: Option<u64>,
+        cache_dir: Option<&str>,
+    ) -> Self {
+        Self {
+            enabled: enabled.unwrap_or(false),
+            interpreters: interpreters.unwrap_or_default(),
+            timeout: timeout.unwrap_or(10),
+            cache_dir: cache_dir.unwrap_or(".mtoh-cache/exec").into(),
+        }
+    }
+}
+
+impl Default for CodeExec {
+    fn default() -> Self {
+        CodeExec::new(None, None, None, None)
+    }
+}
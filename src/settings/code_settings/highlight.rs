@@ -1,29 +1,38 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct CodeHighlight {
     #[serde(default = "default_code_highlight_theme")]
     pub theme: String,
+    /// language of indented code blocks and fenced code blocks without a language,
+    /// they are not highlighted when empty
+    #[serde(default = "default_code_highlight_default_language")]
+    pub default_language: String,
 }
 
 fn default_code_highlight_theme() -> String {
     CodeHighlight::default().theme
 }
 
+fn default_code_highlight_default_language() -> String {
+    CodeHighlight::default().default_language
+}
+
 impl CodeHighlight {
-    pub fn new(theme: Option<&str>) -> Self {
+    pub fn new(theme: Option<&str>, default_language: Option<&str>) -> Self {
         Self {
             theme: match theme {
                 Some(s) => s,
                 None => "Solarized (light)",
             }
             .into(),
+            default_language: default_language.unwrap_or("").into(),
         }
     }
 }
 
 impl Default for CodeHighlight {
     fn default() -> Self {
-        CodeHighlight::new(None)
+        CodeHighlight::new(None, None)
     }
 }
@@ -1 +1,3 @@
+pub mod check;
+pub mod exec;
 pub mod highlight;
\ No newline at end of file
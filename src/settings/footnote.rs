@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// footnotes need `footnotes = true` in `[markdown]`
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Footnote {
+    /// "endnote" (a list at the end of the page), "sidenote" (next to the reference)
+    /// or "popover" (shown by clicking the reference)
+    #[serde(default = "default_footnote_style")]
+    pub style: String,
+}
+
+fn default_footnote_style() -> String {
+    Footnote::default().style
+}
+
+impl Footnote {
+    pub fn new(style: Option<&str>) -> Self {
+        Self {
+            style: style.unwrap_or("endnote").into(),
+        }
+    }
+}
+
+impl Default for Footnote {
+    fn default() -> Self {
+        Footnote::new(None)
+    }
+}
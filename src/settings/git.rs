@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Git {
+    #[serde(default = "default_git_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_git_cache_file")]
+    pub cache_file: String,
+}
+
+fn default_git_enabled() -> bool {
+    Git::default().enabled
+}
+
+fn default_git_cache_file() -> String {
+    Git::default().cache_file
+}
+
+impl Git {
+    pub fn new(enabled: Option<bool>, cache_file: Option<&str>) -> Self {
+        Self {
+            enabled: enabled.unwrap_or(false),
+            cache_file: cache_file.unwrap_or(".mtoh-cache/git.json").into(),
+        }
+    }
+}
+
+impl Default for Git {
+    fn default() -> Self {
+        Git::new(None, None)
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Heading {
+    /// generates the id of a heading without `{#id}` from its text
+    #[serde(default = "default_heading_ids")]
+    pub ids: bool,
+    /// inserts `<a class="anchor">` linking to the heading itself
+    #[serde(default = "default_heading_anchors")]
+    pub anchors: bool,
+}
+
+fn default_heading_ids() -> bool {
+    Heading::default().ids
+}
+
+fn default_heading_anchors() -> bool {
+    Heading::default().anchors
+}
+
+impl Heading {
+    pub fn new(ids: Option<bool>, anchors: Option<bool>) -> Self {
+        Self {
+            ids: ids.unwrap_or(true),
+            anchors: anchors.unwrap_or(false),
+        }
+    }
+}
+
+impl Default for Heading {
+    fn default() -> Self {
+        Heading::new(None, None)
+    }
+}
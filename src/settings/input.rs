@@ -2,7 +2,7 @@ use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Input {
     #[serde(default = "default_input_markdown_dir")]
     pub markdown_dir: String,
@@ -12,6 +12,9 @@ pub struct Input {
     pub template_dir: String,
     #[serde(default = "default_input_img_dir")]
     pub img_dir: String,
+    /// the Markdown files included by the pages, which are not rendered as pages
+    #[serde(default = "default_input_snippets_dir")]
+    pub snippets_dir: String,
 }
 
 fn default_input_markdown_dir() -> String {
@@ -30,12 +33,17 @@ fn default_input_img_dir() -> String {
     Input::default().img_dir
 }
 
+fn default_input_snippets_dir() -> String {
+    Input::default().snippets_dir
+}
+
 impl Input {
     pub fn new(
         markdown_dir: Option<&str>,
         sass_dir: Option<&str>,
         template_dir: Option<&str>,
         img_dir: Option<&str>,
+        snippets_dir: Option<&str>,
     ) -> Self {
         let md = match markdown_dir {
             Some(s) => s,
@@ -57,12 +65,16 @@ impl Input {
                 Some(s) => s.into(),
                 None => Path::new(&md).join("img").to_string_lossy().into(),
             },
+            snippets_dir: match snippets_dir {
+                Some(s) => s.into(),
+                None => Path::new(&md).join("snippets").to_string_lossy().into(),
+            },
         }
     }
 }
 
 impl Default for Input {
     fn default() -> Self {
-        Input::new(None, None, None, None)
+        Input::new(None, None, None, None, None)
     }
 }
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+
+/// extensions of the Markdown syntax
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Markdown {
+    #[serde(default = "default_markdown_tables")]
+    pub tables: bool,
+    #[serde(default = "default_markdown_footnotes")]
+    pub footnotes: bool,
+    /// `~~text~~`
+    #[serde(default = "default_markdown_strikethrough")]
+    pub strikethrough: bool,
+    /// `- [x] item`
+    #[serde(default = "default_markdown_tasklists")]
+    pub tasklists: bool,
+    /// curly quotes, en and em dashes and ellipses
+    #[serde(default = "default_markdown_smart_punctuation")]
+    pub smart_punctuation: bool,
+    /// `# heading {#id .class}`
+    #[serde(default = "default_markdown_heading_attributes")]
+    pub heading_attributes: bool,
+    /// prefixes the headings with their section numbers (e.g. "1.2"),
+    /// `<!--- :numbered: false --->` in a page overrides it
+    #[serde(default = "default_markdown_number_headings")]
+    pub number_headings: bool,
+    /// `==text==`
+    #[serde(default = "default_markdown_mark")]
+    pub mark: bool,
+    /// `^text^`
+    #[serde(default = "default_markdown_superscript")]
+    pub superscript: bool,
+    /// `~text~`, a single tilde is not a strikethrough then
+    #[serde(default = "default_markdown_subscript")]
+    pub subscript: bool,
+    /// `++text++`
+    #[serde(default = "default_markdown_inserted")]
+    pub inserted: bool,
+    /// `[[Ctrl]]+[[C]]`
+    #[serde(default = "default_markdown_keyboard")]
+    pub keyboard: bool,
+    /// `{漢字|かんじ}`, `{漢字|かん|じ}` (for each character) and `｜漢字《かんじ》` (Aozora Bunko)
+    #[serde(default = "default_markdown_ruby")]
+    pub ruby: bool,
+}
+
+fn default_markdown_tables() -> bool {
+    Markdown::default().tables
+}
+
+fn default_markdown_footnotes() -> bool {
+    Markdown::default().footnotes
+}
+
+fn default_markdown_strikethrough() -> bool {
+    Markdown::default().strikethrough
+}
+
+fn default_markdown_tasklists() -> bool {
+    Markdown::default().tasklists
+}
+
+fn default_markdown_smart_punctuation() -> bool {
+    Markdown::default().smart_punctuation
+}
+
+fn default_markdown_heading_attributes() -> bool {
+    Markdown::default().heading_attributes
+}
+
+fn default_markdown_number_headings() -> bool {
+    Markdown::default().number_headings
+}
+
+fn default_markdown_mark() -> bool {
+    Markdown::default().mark
+}
+
+fn default_markdown_superscript() -> bool {
+    Markdown::default().superscript
+}
+
+fn default_markdown_subscript() -> bool {
+    Markdown::default().subscript
+}
+
+fn default_markdown_inserted() -> bool {
+    Markdown::default().inserted
+}
+
+fn default_markdown_keyboard() -> bool {
+    Markdown::default().keyboard
+}
+
+fn default_markdown_ruby() -> bool {
+    Markdown::default().ruby
+}
+
+impl Markdown {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        tables: Option<bool>,
+        footnotes: Option<bool>,
+        strikethrough: Option<bool>,
+        tasklists: Option<bool>,
+        smart_punctuation: Option<bool>,
+        heading_attributes: Option<bool>,
+        number_headings: Option<bool>,
+        mark: Option<bool>,
+        superscript: Option<bool>,
+        subscript: Option<bool>,
+        inserted: Option<bool>,
+        keyboard: Option<bool>,
+        ruby: Option<bool>,
+    ) -> Self {
+        Self {
+            tables: tables.unwrap_or(true),
+            footnotes: footnotes.unwrap_or(false),
+            strikethrough: strikethrough.unwrap_or(true),
+            tasklists: tasklists.unwrap_or(false),
+            smart_punctuation: smart_punctuation.unwrap_or(false),
+            heading_attributes: heading_attributes.unwrap_or(true),
+            number_headings: number_headings.unwrap_or(false),
+            mark: mark.unwrap_or(false),
+            superscript: superscript.unwrap_or(false),
+            subscript: subscript.unwrap_or(false),
+            inserted: inserted.unwrap_or(false),
+            keyboard: keyboard.unwrap_or(false),
+            ruby: ruby.unwrap_or(false),
+        }
+    }
+
+    /// whether any of the inline syntaxes in the text (e.g. `==text==`) is enabled
+    pub fn inline_markup(&self) -> bool {
+        self.mark
+            || self.superscript
+            || self.subscript
+            || self.inserted
+            || self.keyboard
+            || self.ruby
+    }
+}
+
+impl Default for Markdown {
+    fn default() -> Self {
+        Markdown::new(
+            None, None, None, None, None, None, None, None, None, None, None, None, None,
+        )
+    }
+}
@@ -1,7 +1,14 @@
+pub mod admonition;
 pub mod code;
+pub mod footnote;
+pub mod git;
+pub mod heading;
 pub mod input;
+pub mod markdown;
 pub mod output;
 pub mod settings;
+pub mod template;
+pub mod toc;
 
 pub mod code_settings;
 pub mod output_settings;
\ No newline at end of file
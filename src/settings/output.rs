@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use super::output_settings::image::OutputImage;
 
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Output {
     #[serde(default = "default_output_html_dir")]
     pub html_dir: String,
@@ -14,6 +14,8 @@ pub struct Output {
     pub js_dir: String,
     #[serde(default = "default_output_img_dir")]
     pub img_dir: String,
+    #[serde(default = "default_output_tangle_dir")]
+    pub tangle_dir: String,
 
     #[serde(default)]
     pub image: OutputImage,
@@ -35,12 +37,17 @@ fn default_output_img_dir() -> String {
     Output::default().img_dir
 }
 
+fn default_output_tangle_dir() -> String {
+    Output::default().tangle_dir
+}
+
 impl Output {
     pub fn new(
         html_dir: Option<&str>,
         css_dir: Option<&str>,
         js_dir: Option<&str>,
         img_dir: Option<&str>,
+        tangle_dir: Option<&str>,
         image: Option<OutputImage>,
     ) -> Self {
         let html = match html_dir {
@@ -56,12 +63,13 @@ impl Output {
             },
             js_dir: match js_dir {
                 Some(s) => s.into(),
-                None => Path::new(&html).join("css").to_string_lossy().into(),
+                None => Path::new(&html).join("js").to_string_lossy().into(),
             },
             img_dir: match img_dir {
                 Some(s) => s.into(),
                 None => Path::new(&html).join("img").to_string_lossy().into(),
             },
+            tangle_dir: tangle_dir.unwrap_or("tangle").into(),
             image: match image {
                 Some(o) => o,
                 None => OutputImage::new(None),
@@ -72,6 +80,6 @@ impl Output {
 
 impl Default for Output {
     fn default() -> Self {
-        Output::new(None, None, None, None, None)
+        Output::new(None, None, None, None, None, None)
     }
 }
@@ -1,12 +1,19 @@
-use std::{fs::File, path::Path};
+use std::{collections::HashMap, fs::File, path::Path};
 
 use serde::{Deserialize, Serialize};
 
+use super::admonition::Admonition;
 use super::code::Code;
+use super::footnote::Footnote;
+use super::git::Git;
+use super::heading::Heading;
 use super::input::Input;
+use super::markdown::Markdown;
 use super::output::Output;
+use super::template::Template;
+use super::toc::Toc;
 
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Settings {
     pub version: i32,
     #[serde(default)]
@@ -14,25 +21,72 @@ pub struct Settings {
     #[serde(default)]
     pub output: Output,
     #[serde(default)]
+    pub markdown: Markdown,
+    #[serde(default)]
+    pub heading: Heading,
+    #[serde(default)]
+    pub toc: Toc,
+    #[serde(default)]
+    pub admonition: Admonition,
+    #[serde(default)]
+    pub footnote: Footnote,
+    #[serde(default)]
     pub code: Code,
+    #[serde(default)]
+    pub git: Git,
+    #[serde(default)]
+    pub template: Template,
+    /// free-form `[site]` table, available as `site` in templates
+    #[serde(default)]
+    pub site: HashMap<String, serde_json::Value>,
+    /// free-form `[vars]` table, available as `vars` in templates
+    #[serde(default)]
+    pub vars: HashMap<String, serde_json::Value>,
 }
 
 impl Settings {
-    pub fn new(input: Option<Input>, output: Option<Output>, code: Option<Code>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        input: Option<Input>,
+        output: Option<Output>,
+        markdown: Option<Markdown>,
+        heading: Option<Heading>,
+        toc: Option<Toc>,
+        admonition: Option<Admonition>,
+        footnote: Option<Footnote>,
+        code: Option<Code>,
+        git: Option<Git>,
+        template: Option<Template>,
+    ) -> Self {
         Self {
             version: 1,
             input: match input {
                 Some(o) => o,
-                None => Input::new(None, None, None, None),
+                None => Input::new(None, None, None, None, None),
             },
             output: match output {
                 Some(o) => o,
-                None => Output::new(None, None, None, None, None),
+                None => Output::new(None, None, None, None, None, None),
             },
+            markdown: markdown.unwrap_or_default(),
+            heading: heading.unwrap_or_default(),
+            toc: toc.unwrap_or_default(),
+            admonition: admonition.unwrap_or_default(),
+            footnote: footnote.unwrap_or_default(),
             code: match code {
                 Some(o) => o,
-                None => Code::new(None),
+                None => Code::new(None, None, None),
             },
+            git: match git {
+                Some(o) => o,
+                None => Git::new(None, None),
+            },
+            template: match template {
+                Some(o) => o,
+                None => Template::new(None, None),
+            },
+            site: HashMap::new(),
+            vars: HashMap::new(),
         }
     }
 
@@ -56,3 +110,44 @@ impl Settings {
         Ok(obj)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use super::Settings;
+
+    #[test]
+    fn test_site_and_vars() {
+        let toml = r#"
+version = 1
+
+[site]
+name = "mtoh"
+authors = ["hoge", "fuga"]
+
+[site.analytics]
+id = "UA-1"
+
+[vars]
+year = 2022
+
+[vars.links]
+repo = "https://example.com/mtoh"
+"#;
+        let settings = toml::from_str::<Settings>(toml).unwrap();
+        assert_eq!(settings.site["analytics"], json!({"id": "UA-1"}));
+        assert_eq!(settings.vars["year"], json!(2022));
+
+        // as in the data of the templates
+        let data = json!({"site": settings.site, "vars": settings.vars});
+        let rendered = Handlebars::new()
+            .render_template(
+                "{{site.name}} {{site.analytics.id}} {{site.authors.[1]}} {{vars.year}} {{vars.links.repo}}",
+                &data,
+            )
+            .unwrap();
+        assert_eq!(rendered, "mtoh UA-1 fuga 2022 https://example.com/mtoh");
+    }
+}
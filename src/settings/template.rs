@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    #[serde(default = "default_template_default_layout")]
+    pub default_layout: String,
+    /// default layout for each directory, relative to `input.markdown_dir`
+    #[serde(default)]
+    pub directories: HashMap<String, String>,
+}
+
+fn default_template_default_layout() -> String {
+    Template::default().default_layout
+}
+
+impl Template {
+    pub fn new(default_layout: Option<&str>, directories: Option<HashMap<String, String>>) -> Self {
+        Self {
+            default_layout: default_layout.unwrap_or("code").into(),
+            directories: directories.unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::new(None, None)
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Toc {
+    /// the shallowest heading level in the table of contents
+    #[serde(default = "default_toc_min_depth")]
+    pub min_depth: usize,
+    /// the deepest heading level in the table of contents
+    #[serde(default = "default_toc_max_depth")]
+    pub max_depth: usize,
+}
+
+fn default_toc_min_depth() -> usize {
+    Toc::default().min_depth
+}
+
+fn default_toc_max_depth() -> usize {
+    Toc::default().max_depth
+}
+
+impl Toc {
+    pub fn new(min_depth: Option<usize>, max_depth: Option<usize>) -> Self {
+        Self {
+            min_depth: min_depth.unwrap_or(2),
+            max_depth: max_depth.unwrap_or(3),
+        }
+    }
+}
+
+impl Default for Toc {
+    fn default() -> Self {
+        Toc::new(None, None)
+    }
+}
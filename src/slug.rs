@@ -0,0 +1,50 @@
+/// lowercases `text` and joins its alphanumeric runs with '-'.
+/// non-ASCII letters (e.g. Japanese) are kept as they are.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    let mut separated = false;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            if separated && !slug.is_empty() {
+                slug.push('-');
+            }
+            separated = false;
+            slug.extend(c.to_lowercase());
+        } else {
+            separated = true;
+        }
+    }
+
+    slug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::slugify;
+
+    #[test]
+    fn test_slugify_ascii() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  --Getting   Started--  "), "getting-started");
+        assert_eq!(slugify("snake_case and v1.2"), "snake_case-and-v1-2");
+    }
+
+    #[test]
+    fn test_slugify_japanese() {
+        assert_eq!(slugify("はじめに"), "はじめに");
+        assert_eq!(
+            slugify("インストール　手順 (Linux)"),
+            "インストール-手順-linux"
+        );
+
+        // with surrogate
+        assert_eq!(slugify("𩸽の食べ方"), "𩸽の食べ方");
+    }
+
+    #[test]
+    fn test_slugify_emoji() {
+        assert_eq!(slugify("🚀 Launch 🚀"), "launch");
+        assert_eq!(slugify("🚿"), "");
+    }
+}
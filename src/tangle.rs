@@ -0,0 +1,101 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::html_gen;
+use crate::settings::settings::Settings;
+
+/// writes the fenced code blocks marked with `file="path"` into `out_dir`.
+/// blocks targeting the same file are concatenated in document order.
+pub fn run(settings: &Settings, out_dir: &Path) -> Result<(), String> {
+    let options = html_gen::parser_options(settings);
+    let markdown_dir_path = crate::make_md_directory_from(settings)?;
+
+    let mut markdown_files: Vec<PathBuf> = vec![];
+    // the snippets are tangled through the pages including them
+    crate::enum_files(&markdown_dir_path, true, &mut |p: &Path| {
+        if p.is_file() && crate::is_page(settings, p) {
+            markdown_files.push(p.to_path_buf());
+        }
+    })?;
+    markdown_files.sort();
+
+    let mut files: Vec<(String, String)> = vec![];
+    for md in markdown_files.iter() {
+        let text = match fs::read_to_string(md) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", md.to_string_lossy(), e)),
+        };
+
+        for (file, code) in html_gen::tangle(&text, options, md) {
+            if !is_relative_inside(Path::new(&file)) {
+                return Err(format!(
+                    "{}: {} is outside of the output directory",
+                    md.to_string_lossy(),
+                    file
+                ));
+            }
+
+            match files.iter_mut().find(|(f, _)| *f == file) {
+                Some((_, c)) => c.push_str(&code),
+                None => files.push((file, code)),
+            }
+        }
+    }
+
+    for (file, code) in files.iter() {
+        let path = out_dir.join(file);
+        if let Some(parent) = path.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                return Err(format!("{}: {}", parent.to_string_lossy(), e));
+            }
+        }
+
+        if let Err(e) = fs::write(&path, code) {
+            return Err(format!("{}: {}", path.to_string_lossy(), e));
+        }
+        println!("{}", path.to_string_lossy());
+    }
+
+    Ok(())
+}
+
+fn is_relative_inside(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::settings::settings::Settings;
+    use crate::test_dir::TestDir;
+
+    use super::run;
+
+    #[test]
+    fn test_run() {
+        let dir = TestDir::new("test_tangle_run");
+        let page = dir.write(
+            "md/page.md",
+            "```sh file=run.sh\necho page\n```\n\n<!--- :include: snippets/a.md --->\n",
+        );
+        dir.write("md/snippets/a.md", "```sh file=run.sh\necho snippet\n```\n");
+        dir.write("md/img/logo.png", b"\x89PNG");
+
+        let md = page.parent().unwrap();
+        let mut settings =
+            Settings::new(None, None, None, None, None, None, None, None, None, None);
+        settings.input.markdown_dir = md.to_string_lossy().to_string();
+        settings.input.img_dir = md.join("img").to_string_lossy().to_string();
+        settings.input.snippets_dir = md.join("snippets").to_string_lossy().to_string();
+
+        let out_dir = dir.path().join("tangle");
+        run(&settings, &out_dir).unwrap();
+        assert_eq!(
+            "echo page\necho snippet\n",
+            fs::read_to_string(out_dir.join("run.sh")).unwrap()
+        );
+    }
+}
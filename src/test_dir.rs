@@ -0,0 +1,39 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// a directory for the files of a test, removed when dropped
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    /// the process id keeps the directories of the concurrent test runs apart
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("mtoh_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// writes `contents` into `relative`, with the directories of it
+    pub fn write(&self, relative: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.path.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}